@@ -1,4 +1,146 @@
+use chrono_tz::Tz;
+use serenity::all::{
+	ActionRowComponent, ChannelId, CommandInteraction, CommandOptionType, Context, CreateAutocompleteResponse,
+	CreateCommandOption, CreateInteractionResponse, ModalInteraction,
+};
+
+use crate::db::Db;
+use crate::reminders::{excerpt, Reminder};
+use crate::timezones::{format_in_timezone, user_timezone};
+
+pub mod channelrules;
+pub mod editreminder;
+pub mod modlog;
 pub mod myreminders;
+pub mod reminderdelivery;
 pub mod remindme;
+pub mod remindmessage;
+pub mod schedule;
 pub mod selfmute;
+pub mod soliloquy;
+pub mod strikes;
 pub mod threadpin;
+pub mod timezone;
+
+/// Commands needing permissions, whose uses are posted in the mod-log.
+pub const PRIVILEGED: &[&str] = &[
+	schedule::NAME,
+	soliloquy::NAME,
+	channelrules::NAME,
+	modlog::NAME,
+	strikes::NAME,
+];
+
+/// Value of a text input of a submitted modal.
+pub fn input_value<'a>(modal: &'a ModalInteraction, custom_id: &str) -> Option<&'a str> {
+	(modal.data.components.iter())
+		.flat_map(|row| &row.components)
+		.find_map(|component| match component {
+			ActionRowComponent::InputText(input) if input.custom_id == custom_id => input.value.as_deref(),
+			_ => None,
+		})
+}
+
+/// Whether whoever used the command can manage a channel picked in its options,
+/// as Discord only checks that they can use the command at all.
+pub fn can_manage_channel(command: &CommandInteraction, channel_id: ChannelId) -> bool {
+	// resolved channels come with the permissions of whoever used the command
+	(command.data.resolved.channels.get(&channel_id))
+		.and_then(|channel| channel.permissions)
+		.is_some_and(|permissions| permissions.manage_channels())
+}
+
+/// Discord doesn't show more autocomplete choices than that.
+const MAX_CHOICES: usize = 25;
+
+/// Nor choice names longer than that.
+const MAX_CHOICE_LEN: usize = 100;
+
+/// An `id` option for one of the user's reminders, with autocomplete.
+///
+/// Commands using it need to send their autocomplete interactions to [`autocomplete_reminder_id`].
+pub fn reminder_id_option(description: &str) -> CreateCommandOption {
+	CreateCommandOption::new(CommandOptionType::Integer, "id", description).set_autocomplete(true)
+}
+
+/// Suggests the user's reminders matching what they typed so far in a [`reminder_id_option`].
+pub async fn autocomplete_reminder_id(db: &Db, ctx: &Context, autocomplete: &CommandInteraction) {
+	let typed = match autocomplete.data.autocomplete() {
+		Some(option) if option.name == "id" => option.value,
+		_ => "",
+	};
+
+	let tz = user_timezone(db, autocomplete.user.id);
+	let reminders = db.user_reminders(autocomplete.user.id).unwrap();
+
+	let mut response = CreateAutocompleteResponse::new();
+	for (rem, time) in reminder_choices(&reminders, tz, typed) {
+		let message = excerpt(&rem.message, MAX_CHOICE_LEN - time.chars().count() - 3);
+		response = response.add_int_choice(format!("{time} – {message}"), rem.id);
+	}
+
+	let builder = CreateInteractionResponse::Autocomplete(response);
+	if let Err(e) = autocomplete.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to autocomplete: {e}");
+	}
+}
+
+/// The reminders whose ID, time or message contain what the user typed, with their formatted time.
+fn reminder_choices<'a>(reminders: &'a [Reminder], tz: Tz, typed: &str) -> Vec<(&'a Reminder, String)> {
+	let typed = typed.trim().to_lowercase();
+
+	(reminders.iter())
+		.map(|rem| (rem, format_in_timezone(rem.timestamp, tz)))
+		.filter(|(rem, time)| {
+			rem.id.to_string().contains(&typed)
+				|| time.to_lowercase().contains(&typed)
+				|| rem.message.to_lowercase().contains(&typed)
+		})
+		.take(MAX_CHOICES)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use serenity::all::{ChannelId, UserId};
+
+	use super::*;
+	use crate::delivery::Delivery;
+	use crate::mentions::Mentions;
+
+	fn reminder(id: i64, timestamp: i64, message: &str) -> Reminder {
+		Reminder {
+			id,
+			timestamp,
+			user_id: UserId::new(1),
+			channel_id: ChannelId::new(1),
+			guild_id: None,
+			message: message.to_string(),
+			recurrence: None,
+			deliver: Delivery::Channel,
+			mentions: Mentions::default(),
+			subscribers: Vec::new(),
+			post: None,
+			retry: None,
+		}
+	}
+
+	#[test]
+	fn reminder_choices_match_id_time_or_message() {
+		let reminders = [
+			reminder(3, 1_800_000_000, "Water the plants"),
+			reminder(12, 1_900_000_000, "Call mom"),
+		];
+		let ids = |typed| {
+			(reminder_choices(&reminders, chrono_tz::UTC, typed).iter())
+				.map(|(rem, _)| rem.id)
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(ids(""), [3, 12]);
+		assert_eq!(ids("12"), [12]);
+		assert_eq!(ids("PLANTS"), [3]);
+		assert_eq!(ids("2030-"), [12]);
+		assert_eq!(ids("nope"), Vec::<i64>::new());
+	}
+}
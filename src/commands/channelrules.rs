@@ -0,0 +1,188 @@
+use serenity::all::{
+	ChannelType, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+	CreateAllowedMentions, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+	CreateInteractionResponseMessage, GuildId, InteractionContext, Permissions,
+};
+
+use crate::commands::can_manage_channel;
+use crate::db::Db;
+use crate::rules::{ChannelRule, Rule, KINDS};
+
+pub const NAME: &str = "channelrules";
+pub const DESCRIPTION: &str = "Set rules for messages in a channel, and I'll delete the ones breaking them~";
+
+/// Channels that can have rules. Threads get those of their channel instead.
+pub const CHANNEL_TYPES: [ChannelType; 3] = [ChannelType::Text, ChannelType::News, ChannelType::Forum];
+
+/// Longest custom violation message, leaving room for the original message in the DM.
+const MAX_MESSAGE_LEN: u16 = 1000;
+
+pub fn register() -> CreateCommand {
+	let kind_option = KINDS.iter().fold(
+		CreateCommandOption::new(CommandOptionType::String, "rule", "What to enforce").required(true),
+		|option, (kind, name)| option.add_string_choice(*name, *kind),
+	);
+
+	CreateCommand::new(NAME)
+		.description(DESCRIPTION)
+		.contexts(vec![InteractionContext::Guild])
+		.default_member_permissions(Permissions::MANAGE_CHANNELS)
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::SubCommand, "add", "Add a rule to a channel")
+				.add_sub_option(
+					CreateCommandOption::new(CommandOptionType::Channel, "channel", "Which channel")
+						.channel_types(CHANNEL_TYPES.to_vec())
+						.required(true),
+				)
+				.add_sub_option(kind_option)
+				.add_sub_option(CreateCommandOption::new(
+					CommandOptionType::String,
+					"value",
+					"For max length, slowmode and deny lists: characters, minutes, or a case insensitive regex",
+				))
+				.add_sub_option(
+					CreateCommandOption::new(
+						CommandOptionType::String,
+						"message",
+						"What to tell whoever breaks the rule",
+					)
+					.max_length(MAX_MESSAGE_LEN),
+				)
+				.add_sub_option(CreateCommandOption::new(
+					CommandOptionType::Boolean,
+					"threads",
+					"Also enforce it in the threads and forum posts of the channel",
+				)),
+		)
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "Remove a rule").add_sub_option(
+				CreateCommandOption::new(CommandOptionType::Integer, "id", "Rule ID, from /channelrules list")
+					.required(true),
+			),
+		)
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::SubCommand, "list", "Show the rules of this server")
+				.add_sub_option(CreateCommandOption::new(
+					CommandOptionType::Channel,
+					"channel",
+					"Only show the rules of this channel",
+				)),
+		)
+}
+
+pub async fn run(db: &Db, ctx: &Context, command: &CommandInteraction) {
+	let Some(guild_id) = command.guild_id else {
+		tracing::error!("/{NAME} used outside of a server");
+		return;
+	};
+
+	let Some(subcommand) = command.data.options.first() else {
+		tracing::error!("No subcommand given to /{NAME}");
+		return;
+	};
+
+	let CommandDataOptionValue::SubCommand(options) = &subcommand.value else {
+		tracing::error!("Invalid subcommand {:?}", subcommand.name);
+		return;
+	};
+
+	let content = match subcommand.name.as_str() {
+		"add" => add(db, guild_id, command, options),
+		"remove" => {
+			let id = (options.iter())
+				.find(|option| option.name == "id")
+				.and_then(|option| option.value.as_i64())
+				.unwrap_or_default();
+
+			match db.delete_channel_rule(guild_id, id).unwrap() {
+				true => format!("Okie, rule `{id}` is gone~"),
+				false => format!("There's no rule `{id}` in this server :c"),
+			}
+		}
+		"list" => {
+			let channel_id = (options.iter())
+				.find(|option| option.name == "channel")
+				.and_then(|option| option.value.as_channel_id());
+
+			let rules = db.guild_rules(guild_id).unwrap();
+			let lines: Vec<_> = (rules.iter())
+				.filter(|rule| channel_id.is_none_or(|channel_id| rule.channel_id == channel_id))
+				.map(list_line)
+				.collect();
+
+			match lines.is_empty() {
+				true => "No rules here~ Add some with `/channelrules add`!".to_string(),
+				false => format!("Channel rules:\n{}", lines.join("\n")),
+			}
+		}
+		s => {
+			tracing::error!("Invalid subcommand {s:?}");
+			return;
+		}
+	};
+
+	// custom messages are shown as written, without pinging anyone
+	let response_message = CreateInteractionResponseMessage::new()
+		.ephemeral(true)
+		.content(content)
+		.allowed_mentions(CreateAllowedMentions::new());
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
+}
+
+fn add(db: &Db, guild_id: GuildId, command: &CommandInteraction, options: &[CommandDataOption]) -> String {
+	let mut channel_id = None;
+	let mut kind = "";
+	let mut value = None;
+	let mut message = None;
+	let mut threads = false;
+
+	for option in options {
+		match option.name.as_str() {
+			"channel" => channel_id = option.value.as_channel_id(),
+			"rule" => kind = option.value.as_str().unwrap_or_default(),
+			"value" => value = option.value.as_str(),
+			"message" => message = option.value.as_str().map(str::to_string),
+			"threads" => threads = option.value.as_bool().unwrap_or_default(),
+			s => tracing::error!("Invalid option {s:?}"),
+		}
+	}
+
+	let channel_id = channel_id.unwrap();
+	if !can_manage_channel(command, channel_id) {
+		return format!("You can't manage <#{channel_id}>, sorry :c");
+	}
+
+	let rule = match Rule::parse(kind, value) {
+		Ok(rule) => rule,
+		Err(e) => return e,
+	};
+
+	let mut rule = ChannelRule {
+		id: 0,
+		channel_id,
+		rule,
+		message,
+		threads,
+	};
+	rule.id = db.add_channel_rule(guild_id, &rule).unwrap();
+
+	format!(
+		"Okie, I'll enforce this in <#{channel_id}>~\n{}\nRemove it with `/channelrules remove id:{}`",
+		list_line(&rule),
+		rule.id
+	)
+}
+
+fn list_line(rule: &ChannelRule) -> String {
+	let mut line = format!("`{}` <#{}>: {}", rule.id, rule.channel_id, rule.rule);
+	if rule.threads {
+		line += ", in threads too";
+	}
+	if let Some(message) = &rule.message {
+		line += &format!(" – “{message}”");
+	}
+	line
+}
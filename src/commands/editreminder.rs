@@ -0,0 +1,163 @@
+use chrono::TimeZone;
+use chrono_tz::Tz;
+use serenity::all::{
+	CommandInteraction, Context, CreateActionRow, CreateCommand, CreateInputText, CreateInteractionResponse,
+	CreateInteractionResponseMessage, CreateModal, InputTextStyle, ModalInteraction,
+};
+
+use crate::commands::remindme::{WRONG, WRONG_REPEAT};
+use crate::commands::{input_value, reminder_id_option};
+use crate::db::Db;
+use crate::limits::{self, Limits};
+use crate::reminders::date_time_now;
+use crate::time_expr::{error_message, parse_recurrence, parse_time_expr};
+use crate::timezones::user_timezone;
+
+pub const NAME: &str = "editreminder";
+pub const DESCRIPTION: &str = "Change the time or message of one of your reminders~";
+
+/// Prefix of the custom ID of the edit modal, followed by the ID of the reminder being edited.
+pub const MODAL_ID_PREFIX: &str = "editreminder:";
+
+/// How the current time is pre-filled in the modal, in a format `/remindme` understands.
+const TIME_INPUT_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+pub fn register() -> CreateCommand {
+	CreateCommand::new(NAME)
+		.description(DESCRIPTION)
+		.add_option(reminder_id_option("ID of the reminder you want to edit").required(true))
+}
+
+pub async fn run(db: &Db, ctx: &Context, command: &CommandInteraction) {
+	let mut rem_id = None;
+
+	for option in &command.data.options {
+		match option.name.as_str() {
+			"id" => rem_id = option.value.as_i64(),
+			s => tracing::error!("Invalid option {s:?}"),
+		}
+	}
+
+	let rem = rem_id.and_then(|rem_id| db.user_reminder(command.user.id, rem_id).unwrap());
+
+	let builder = match rem {
+		Some(rem) if rem.user_id != command.user.id => {
+			CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().ephemeral(true).content(
+				format!("Only <@{}> can edit this one, you just joined it~", rem.user_id),
+			))
+		}
+		Some(rem) => {
+			let tz = user_timezone(db, command.user.id);
+
+			let time = CreateInputText::new(InputTextStyle::Short, "Time", "time")
+				.value(format_time_input(rem.timestamp, tz))
+				.placeholder("tomorrow at 9, in 3h, 2027-06-10 12:23...");
+			let message = CreateInputText::new(InputTextStyle::Paragraph, "Message", "message")
+				.value(&rem.message)
+				.max_length(4000);
+			let repeat = CreateInputText::new(InputTextStyle::Short, "Repeat", "repeat")
+				.value(
+					rem.recurrence
+						.map(|recurrence| recurrence.to_string())
+						.unwrap_or_default(),
+				)
+				.placeholder("every 1d, every monday 09:00... (leave empty to not repeat)")
+				.required(false);
+
+			let modal = CreateModal::new(
+				format!("{MODAL_ID_PREFIX}{}", rem.id),
+				format!("Edit reminder {}", rem.id),
+			)
+			.components(vec![
+				CreateActionRow::InputText(time),
+				CreateActionRow::InputText(message),
+				CreateActionRow::InputText(repeat),
+			]);
+
+			CreateInteractionResponse::Modal(modal)
+		}
+		None => CreateInteractionResponse::Message(
+			CreateInteractionResponseMessage::new()
+				.ephemeral(true)
+				.content("No such reminder :("),
+		),
+	};
+
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
+}
+
+fn format_time_input(timestamp: i64, tz: Tz) -> String {
+	tz.timestamp_opt(timestamp, 0)
+		.unwrap()
+		.format(TIME_INPUT_FORMAT)
+		.to_string()
+}
+
+pub async fn handle_modal(db: &Db, limits: &Limits, ctx: &Context, modal: &ModalInteraction) {
+	let custom_id = &modal.data.custom_id;
+	let Some(rem_id) = (custom_id.strip_prefix(MODAL_ID_PREFIX)).and_then(|id| id.parse::<i64>().ok()) else {
+		tracing::error!("Invalid edit modal {custom_id:?}");
+		return;
+	};
+
+	let now = date_time_now();
+	let tz = user_timezone(db, modal.user.id);
+
+	let time = input_value(modal, "time").unwrap_or_default().trim();
+	let message = input_value(modal, "message").unwrap_or_default();
+	let repeat = input_value(modal, "repeat").unwrap_or_default().trim();
+
+	let content: String = 'content: {
+		let recurrence = if repeat.is_empty() {
+			None
+		} else {
+			match parse_recurrence(repeat) {
+				Ok(recurrence) => Some(recurrence),
+				Err(e) => break 'content error_message(&e, repeat) + WRONG_REPEAT,
+			}
+		};
+
+		let rem = db.user_reminder(modal.user.id, rem_id).unwrap();
+		let Some(mut rem) = rem.filter(|rem| rem.user_id == modal.user.id) else {
+			break 'content "No such reminder :( Was it already sent?".to_string();
+		};
+
+		let timestamp = if time == format_time_input(rem.timestamp, tz) {
+			// keep seconds that weren't shown in the modal
+			rem.timestamp
+		} else {
+			match parse_time_expr(&tz.from_utc_datetime(&now.naive_utc()), time) {
+				Ok(date_time) => date_time.timestamp(),
+				Err(e) => break 'content error_message(&e, time) + WRONG,
+			}
+		};
+
+		if timestamp <= now.timestamp() {
+			break 'content "Sweetie, I don't have a time machine! :c".to_string();
+		}
+
+		rem.timestamp = timestamp;
+		rem.message = message.to_string();
+		rem.recurrence = recurrence;
+		rem.retry = None;
+
+		if let Err(e) = limits.check_content(&rem, now.timestamp()) {
+			break 'content limits::error_message(&e);
+		}
+
+		db.update_reminder(&rem).unwrap();
+
+		match recurrence {
+			Some(recurrence) => format!("Okie, reminder `{rem_id}` is now <t:{timestamp}:F>, and then {recurrence} ~"),
+			None => format!("Okie, reminder `{rem_id}` is now <t:{timestamp}:F> ~"),
+		}
+	};
+
+	let response_message = CreateInteractionResponseMessage::new().ephemeral(true).content(content);
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = modal.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to modal: {e}");
+	}
+}
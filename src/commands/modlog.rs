@@ -0,0 +1,82 @@
+use serenity::all::{
+	ChannelType, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommand,
+	CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+use crate::db::Db;
+
+pub const NAME: &str = "modlog";
+pub const DESCRIPTION: &str = "Choose where I log the messages I delete and the moderation commands used~";
+
+pub fn register() -> CreateCommand {
+	CreateCommand::new(NAME)
+		.description(DESCRIPTION)
+		.contexts(vec![InteractionContext::Guild])
+		.default_member_permissions(Permissions::MANAGE_GUILD)
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Log in this channel").add_sub_option(
+				CreateCommandOption::new(CommandOptionType::Channel, "channel", "Where to log")
+					.channel_types(vec![
+						ChannelType::Text,
+						ChannelType::PrivateThread,
+						ChannelType::PublicThread,
+					])
+					.required(true),
+			),
+		)
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::SubCommand,
+			"off",
+			"Stop logging",
+		))
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::SubCommand,
+			"show",
+			"Show where I'm logging",
+		))
+}
+
+pub async fn run(db: &Db, ctx: &Context, command: &CommandInteraction) {
+	let Some(guild_id) = command.guild_id else {
+		tracing::error!("/{NAME} used outside of a server");
+		return;
+	};
+
+	let Some(subcommand) = command.data.options.first() else {
+		tracing::error!("No subcommand given to /{NAME}");
+		return;
+	};
+
+	let content = match (subcommand.name.as_str(), &subcommand.value) {
+		("set", CommandDataOptionValue::SubCommand(options)) => {
+			let Some(channel_id) = (options.iter())
+				.find(|option| option.name == "channel")
+				.and_then(|option| option.value.as_channel_id())
+			else {
+				tracing::error!("No channel given to /{NAME} set");
+				return;
+			};
+
+			db.set_mod_log_channel(guild_id, Some(channel_id)).unwrap();
+			format!("Okie, I'll log what happens in <#{channel_id}>~ Make sure I can post there!")
+		}
+		("off", _) => {
+			db.set_mod_log_channel(guild_id, None).unwrap();
+			"Okie, I won't log anything anymore~".to_string()
+		}
+		("show", _) => match db.mod_log_channel(guild_id).unwrap() {
+			Some(channel_id) => format!("I'm logging in <#{channel_id}>~"),
+			None => "I'm not logging anything in this server~ Choose a channel with `/modlog set`!".to_string(),
+		},
+		(s, _) => {
+			tracing::error!("Invalid subcommand {s:?}");
+			return;
+		}
+	};
+
+	let response_message = CreateInteractionResponseMessage::new().ephemeral(true).content(content);
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
+}
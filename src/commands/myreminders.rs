@@ -1,138 +1,751 @@
-use std::{
-	collections::{HashSet, VecDeque},
-	fmt::Write as _,
-	ops::Deref,
-	sync::{Arc, RwLock},
-};
+use std::fmt::{self, Write as _};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
+use chrono_tz::Tz;
 use serenity::all::{
-	CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, CreateInteractionResponse,
-	CreateInteractionResponseMessage,
+	ButtonStyle, ChannelId, CommandInteraction, CommandOptionType, ComponentInteraction, ComponentInteractionDataKind,
+	Context, CreateActionRow, CreateAttachment, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+	CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu,
+	CreateSelectMenuKind, CreateSelectMenuOption, UserId,
 };
 
-use crate::reminders::{store_reminders, Reminder};
+use crate::commands::reminder_id_option;
+use crate::db::Db;
+use crate::delivery::Delivery;
+use crate::ical;
+use crate::reminders::{date_time_now, excerpt, Recurrence, Reminder, MAX_SEND_ATTEMPTS};
+use crate::timezones::{format_in_timezone, user_timezone};
 
 pub const NAME: &str = "myreminders";
 pub const DESCRIPTION: &str = "I'll list all your reminders~ ♡";
 
+/// Prefix of the custom IDs of the list's buttons and select menu.
+///
+/// They look like `myreminders:{action}:{page}:{sort}:{channel}[:{reminder id}]`,
+/// the channel being 0 when the list isn't filtered.
+pub const CUSTOM_ID_PREFIX: &str = "myreminders:";
+
+const PAGE_LEN: usize = 10;
+
+/// Pink~
+const EMBED_COLOR: u32 = 0xF5A9B8;
+
+/// Order of the reminders in the list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Sort {
+	/// Soonest first.
+	Time,
+	/// Most recently created first.
+	Created,
+}
+
+impl Sort {
+	fn from_option(value: &str) -> Option<Self> {
+		match value {
+			"time" => Some(Sort::Time),
+			"created" => Some(Sort::Created),
+			_ => None,
+		}
+	}
+
+	fn to_option(self) -> &'static str {
+		match self {
+			Sort::Time => "time",
+			Sort::Created => "created",
+		}
+	}
+}
+
+/// What the list shows, carried around in the custom IDs of its components.
+#[derive(Clone, Copy, Debug)]
+struct ListState {
+	page: usize,
+	sort: Sort,
+	channel: Option<ChannelId>,
+}
+
+impl ListState {
+	fn custom_id(&self, action: &str) -> String {
+		format!(
+			"{CUSTOM_ID_PREFIX}{action}:{}:{}:{}",
+			self.page,
+			self.sort.to_option(),
+			self.channel.map_or(0, |channel| channel.get())
+		)
+	}
+
+	/// Parses a custom ID into its action, list state and the rest.
+	fn parse(custom_id: &str) -> Option<(&str, Self, Option<&str>)> {
+		let mut parts = custom_id.strip_prefix(CUSTOM_ID_PREFIX)?.splitn(5, ':');
+
+		let action = parts.next()?;
+		let page = parts.next()?.parse().ok()?;
+		let sort = Sort::from_option(parts.next()?)?;
+		let channel = match parts.next()?.parse::<u64>().ok()? {
+			0 => None,
+			channel => Some(ChannelId::new(channel)),
+		};
+
+		Some((action, ListState { page, sort, channel }, parts.next()))
+	}
+}
+
 pub fn register() -> CreateCommand {
 	CreateCommand::new(NAME)
-		.add_option(CreateCommandOption::new(
-			CommandOptionType::Integer,
-			"id",
+		.add_option(reminder_id_option(
 			"ID of the reminder you want to see (all if not specified)",
 		))
 		.add_option(CreateCommandOption::new(
 			CommandOptionType::Boolean,
 			"delete",
-			"Delete the specified reminder, or all of them (Careful, no confirm button!)",
+			"Delete the specified reminder or all listed ones, or leave the ones you joined (I'll ask first~)",
+		))
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::Channel,
+			"channel",
+			"Only the reminders of this channel",
+		))
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::String, "sort", "How to sort the list")
+				.add_string_choice("Soonest first", "time")
+				.add_string_choice("Newest first", "created"),
+		)
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::Boolean,
+			"export",
+			"Get the listed reminders as a calendar file (.ics) for your calendar app",
 		))
 		.description(DESCRIPTION)
 }
 
-pub async fn run(reminders: Arc<RwLock<VecDeque<Reminder>>>, ctx: &Context, command: &CommandInteraction) {
+/// A reminder on a single line, for the list.
+fn list_line(rem: &Reminder, user_id: UserId, tz: Tz) -> String {
+	// replace backticks with grave accent to avoid breaking the display
+	let message = excerpt(&rem.message, 40).replace('`', "ˋ");
+
+	let mut line = format!(
+		"`{}` {} (<t:{}:R>) in <#{}> `{}`",
+		rem.id,
+		format_in_timezone(rem.timestamp, tz),
+		rem.timestamp,
+		rem.channel_id,
+		message
+	);
+
+	if let Some(recurrence) = rem.recurrence {
+		write!(&mut line, " (repeats {recurrence})").unwrap();
+	}
+
+	if rem.deliver != Delivery::Channel {
+		write!(&mut line, " (sent {})", rem.deliver).unwrap();
+	}
+
+	if !rem.mentions.is_empty() {
+		write!(&mut line, " (pings {})", rem.mentions).unwrap();
+	}
+
+	if rem.post.is_some() {
+		write!(&mut line, " (scheduled post)").unwrap();
+	}
+
+	if rem.user_id != user_id {
+		write!(&mut line, " (joined, from <@{}>)", rem.user_id).unwrap();
+	} else if !rem.subscribers.is_empty() {
+		write!(&mut line, " ({} joined)", rem.subscribers.len()).unwrap();
+	}
+
+	if rem.retry.is_some() {
+		write!(&mut line, " (pending retry)").unwrap();
+	}
+
+	line
+}
+
+/// The reminders of the user matching the list's filter, in its order.
+fn listed_reminders(db: &Db, user_id: UserId, state: &ListState) -> Vec<Reminder> {
+	let mut reminders = db.user_reminders(user_id).unwrap();
+
+	if let Some(channel) = state.channel {
+		reminders.retain(|rem| rem.channel_id == channel);
+	}
+
+	if state.sort == Sort::Created {
+		reminders.sort_by_key(|rem| std::cmp::Reverse(rem.id));
+	}
+
+	reminders
+}
+
+/// A page of the list, with buttons to go through the pages and a menu to open a reminder.
+fn list_view(db: &Db, user_id: UserId, mut state: ListState) -> (CreateEmbed, Vec<CreateActionRow>) {
+	let tz = user_timezone(db, user_id);
+	let reminders = listed_reminders(db, user_id, &state);
+
+	let page_count = reminders.len().div_ceil(PAGE_LEN).max(1);
+	state.page = state.page.min(page_count - 1);
+
+	let page = (reminders.iter())
+		.skip(state.page * PAGE_LEN)
+		.take(PAGE_LEN)
+		.collect::<Vec<_>>();
+
+	let mut description = if page.is_empty() {
+		String::from("You have no reminders! Sorry~")
+	} else {
+		String::new()
+	};
+
+	for rem in &page {
+		writeln!(&mut description, "- {}", list_line(rem, user_id, tz)).unwrap();
+	}
+
+	let mut footer = format!("Page {} of {page_count} · ", state.page + 1);
+	match reminders.len() {
+		1 => footer += "1 reminder",
+		l => footer += &format!("{l} reminders"),
+	}
+
+	let mut title = String::from("Here are your reminders~");
+	if let Some(channel) = state.channel {
+		description = format!("In <#{channel}>\n\n{description}");
+		title = String::from("Here are your reminders in this channel~");
+	}
+
+	let embed = CreateEmbed::new()
+		.title(title)
+		.description(description)
+		.footer(CreateEmbedFooter::new(footer))
+		.color(EMBED_COLOR);
+
+	let mut components = Vec::new();
+
+	if !page.is_empty() {
+		let options = (page.iter())
+			.map(|rem| {
+				CreateSelectMenuOption::new(
+					format!("{} · {}", rem.id, format_in_timezone(rem.timestamp, tz)),
+					rem.id.to_string(),
+				)
+				.description(excerpt(&rem.message, 100))
+			})
+			.collect();
+
+		let menu = CreateSelectMenu::new(state.custom_id("open"), CreateSelectMenuKind::String { options })
+			.placeholder("Open a reminder~");
+		components.push(CreateActionRow::SelectMenu(menu));
+	}
+
+	if page_count > 1 {
+		let prev = ListState {
+			page: state.page.saturating_sub(1),
+			..state
+		};
+		let next = ListState {
+			page: state.page + 1,
+			..state
+		};
+
+		components.push(CreateActionRow::Buttons(vec![
+			CreateButton::new(prev.custom_id("prev"))
+				.label("◀ Previous")
+				.style(ButtonStyle::Secondary)
+				.disabled(state.page == 0),
+			CreateButton::new(next.custom_id("next"))
+				.label("Next ▶")
+				.style(ButtonStyle::Secondary)
+				.disabled(state.page + 1 >= page_count),
+		]));
+	}
+
+	(embed, components)
+}
+
+/// A single reminder in full.
+fn reminder_embed(rem: &Reminder, user_id: UserId, tz: Tz) -> CreateEmbed {
+	let mut description = format!(
+		"<t:{}:F> ({}) in <#{}>",
+		rem.timestamp,
+		format_in_timezone(rem.timestamp, tz),
+		rem.channel_id
+	);
+
+	if let Some(recurrence) = rem.recurrence {
+		write!(
+			&mut description,
+			"\nRepeats {recurrence}, next one <t:{}:R>",
+			rem.timestamp
+		)
+		.unwrap();
+	}
+
+	if rem.deliver != Delivery::Channel {
+		write!(&mut description, "\nSent {}", rem.deliver).unwrap();
+	}
+
+	if !rem.mentions.is_empty() {
+		write!(&mut description, "\nPings {}", rem.mentions).unwrap();
+	}
+
+	if let Some(post) = &rem.post {
+		description += "\nScheduled post, no ping prefix";
+		if post.announce {
+			description += ", published to the servers following the channel";
+		}
+		if let Some(title) = &post.embed_title {
+			write!(&mut description, "\nEmbed title: {title}").unwrap();
+		}
+		if let Some(embed) = &post.embed_description {
+			write!(&mut description, "\nEmbed: {}", excerpt(embed, 200)).unwrap();
+		}
+	}
+
+	if rem.user_id != user_id {
+		write!(&mut description, "\nMade by <@{}>, you joined it", rem.user_id).unwrap();
+	}
+
+	if !rem.subscribers.is_empty() {
+		let subscribers = (rem.subscribers.iter())
+			.map(|subscriber| format!("<@{subscriber}>"))
+			.collect::<Vec<_>>();
+		write!(&mut description, "\nJoined by {}", subscribers.join(" ")).unwrap();
+	}
+
+	if let Some(retry) = rem.retry {
+		write!(
+			&mut description,
+			"\nI couldn't send it <t:{}:R>, trying again <t:{}:R> (attempt {} of {MAX_SEND_ATTEMPTS})",
+			retry.due,
+			rem.timestamp,
+			retry.attempts + 1
+		)
+		.unwrap();
+	}
+
+	description += "\n\n";
+	description += &rem.message;
+
+	CreateEmbed::new()
+		.title(format!("Reminder `{}`", rem.id))
+		.description(description)
+		.color(EMBED_COLOR)
+}
+
+/// A single reminder, with buttons to delete it or go back to the list.
+fn reminder_view(rem: &Reminder, user_id: UserId, tz: Tz, state: ListState) -> (CreateEmbed, Vec<CreateActionRow>) {
+	let buttons = vec![
+		CreateButton::new(state.custom_id("list"))
+			.label("Back to the list")
+			.style(ButtonStyle::Secondary),
+		CreateButton::new(format!("{}:{}", state.custom_id("delete"), Target::One(rem.id)))
+			.label(if rem.user_id == user_id { "Delete" } else { "Leave" })
+			.style(ButtonStyle::Danger),
+	];
+
+	(
+		reminder_embed(rem, user_id, tz),
+		vec![CreateActionRow::Buttons(buttons)],
+	)
+}
+
+/// How long deleted reminders can be put back.
+const UNDO_WINDOW_SECS: i64 = 5 * 60;
+
+/// How many reminders the confirmation lists before giving up and just counting.
+const CONFIRM_MAX_LINES: usize = 20;
+
+/// What a delete button is about to delete.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Target {
+	/// A single reminder.
+	One(i64),
+	/// All the listed reminders, up to the newest one shown in the confirmation,
+	/// so that reminders made in the meantime don't get caught in it.
+	All { max_id: i64 },
+}
+
+impl Target {
+	fn parse(value: &str) -> Option<Self> {
+		match value.strip_prefix("all-") {
+			Some(max_id) => Some(Target::All {
+				max_id: max_id.parse().ok()?,
+			}),
+			None => Some(Target::One(value.parse().ok()?)),
+		}
+	}
+
+	/// The user's reminders it points to.
+	fn reminders(self, db: &Db, user_id: UserId, state: &ListState) -> Vec<Reminder> {
+		match self {
+			Target::One(id) => db.user_reminder(user_id, id).unwrap().into_iter().collect(),
+			Target::All { max_id } => {
+				let mut reminders = listed_reminders(db, user_id, state);
+				reminders.retain(|rem| rem.id <= max_id);
+				reminders
+			}
+		}
+	}
+}
+
+impl fmt::Display for Target {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Target::One(id) => write!(f, "{id}"),
+			Target::All { max_id } => write!(f, "all-{max_id}"),
+		}
+	}
+}
+
+/// Asks whether to really delete these reminders.
+fn confirm_view(
+	reminders: &[Reminder],
+	user_id: UserId,
+	tz: Tz,
+	state: ListState,
+	target: Target,
+) -> (CreateEmbed, Vec<CreateActionRow>) {
+	let title = match reminders.len() {
+		1 => String::from("Delete this reminder?"),
+		l => format!("Delete these {l} reminders?"),
+	};
+
+	let mut description = String::new();
+	for rem in reminders.iter().take(CONFIRM_MAX_LINES) {
+		writeln!(&mut description, "- {}", list_line(rem, user_id, tz)).unwrap();
+	}
+
+	if reminders.len() > CONFIRM_MAX_LINES {
+		writeln!(&mut description, "...and {} more", reminders.len() - CONFIRM_MAX_LINES).unwrap();
+	}
+
+	let embed = CreateEmbed::new()
+		.title(title)
+		.description(description)
+		.color(EMBED_COLOR);
+
+	let buttons = vec![
+		CreateButton::new(format!("{}:{target}", state.custom_id("confirm")))
+			.label("Delete")
+			.style(ButtonStyle::Danger),
+		CreateButton::new(state.custom_id("cancel"))
+			.label("Cancel")
+			.style(ButtonStyle::Secondary),
+	];
+
+	(embed, vec![CreateActionRow::Buttons(buttons)])
+}
+
+/// Reminders that were just deleted, kept around for a little while in case the user changes their mind.
+struct DeletedBatch {
+	id: u64,
+	user_id: UserId,
+	deleted_at: i64,
+	reminders: Vec<Reminder>,
+}
+
+static NEXT_BATCH_ID: AtomicU64 = AtomicU64::new(1);
+static RECENTLY_DELETED: Mutex<Vec<DeletedBatch>> = Mutex::new(Vec::new());
+
+/// Keeps deleted reminders for [`UNDO_WINDOW_SECS`], and returns the ID to put them back with.
+fn remember_deleted(user_id: UserId, reminders: Vec<Reminder>, now: i64) -> u64 {
+	let id = NEXT_BATCH_ID.fetch_add(1, Ordering::Relaxed);
+
+	let mut deleted = RECENTLY_DELETED.lock().unwrap();
+	deleted.retain(|batch| now - batch.deleted_at <= UNDO_WINDOW_SECS);
+	deleted.push(DeletedBatch {
+		id,
+		user_id,
+		deleted_at: now,
+		reminders,
+	});
+
+	id
+}
+
+/// Takes back the reminders the user deleted, if it wasn't too long ago.
+fn take_deleted(user_id: UserId, batch_id: u64, now: i64) -> Option<Vec<Reminder>> {
+	let mut deleted = RECENTLY_DELETED.lock().unwrap();
+	let index = (deleted.iter()).position(|batch| batch.id == batch_id && batch.user_id == user_id)?;
+	let batch = deleted.swap_remove(index);
+
+	(now - batch.deleted_at <= UNDO_WINDOW_SECS).then_some(batch.reminders)
+}
+
+/// Deletes the reminders, and shows the list with a button to undo it.
+fn delete_and_list(
+	db: &Db,
+	user_id: UserId,
+	reminders: Vec<Reminder>,
+	state: ListState,
+) -> CreateInteractionResponseMessage {
+	let ids = reminders.iter().map(|rem| rem.id).collect::<Vec<_>>();
+	let deleted = db.delete_user_reminders(user_id, &ids).unwrap();
+
+	let content = match deleted {
+		0 => return list_message(db, user_id, state, "No such reminder :( Was it already sent?"),
+		1 => "1 reminder deleted!".to_string(),
+		l => format!("{l} reminders deleted!"),
+	};
+
+	let batch = remember_deleted(user_id, reminders, date_time_now().timestamp());
+
+	let (embed, mut components) = list_view(db, user_id, state);
+	let undo = CreateButton::new(format!("{}:{batch}", state.custom_id("undo")))
+		.label("Undo")
+		.style(ButtonStyle::Secondary);
+	components.insert(0, CreateActionRow::Buttons(vec![undo]));
+
+	(CreateInteractionResponseMessage::new().content(content))
+		.embed(embed)
+		.components(components)
+}
+
+/// The list, with a little note on top.
+fn list_message(db: &Db, user_id: UserId, state: ListState, content: &str) -> CreateInteractionResponseMessage {
+	let (embed, components) = list_view(db, user_id, state);
+	(CreateInteractionResponseMessage::new().content(content))
+		.embed(embed)
+		.components(components)
+}
+
+pub async fn run(db: &Db, ctx: &Context, command: &CommandInteraction) {
+	let tz = user_timezone(db, command.user.id);
+
 	let mut rem_id = None;
 	let mut delet = false;
+	let mut export = false;
+	let mut state = ListState {
+		page: 0,
+		sort: Sort::Time,
+		channel: None,
+	};
 
 	for option in &command.data.options {
 		match option.name.as_str() {
 			"id" => rem_id = Some(option.value.as_i64().unwrap()),
 			"delete" => delet = option.value.as_bool().unwrap_or_default(),
+			"export" => export = option.value.as_bool().unwrap_or_default(),
+			"channel" => state.channel = option.value.as_channel_id(),
+			"sort" => state.sort = option.value.as_str().and_then(Sort::from_option).unwrap_or(Sort::Time),
 			s => tracing::error!("Invalid option {s:?}"),
 		}
 	}
 
-	let mut rems_to_delet = HashSet::new();
+	let response_message = CreateInteractionResponseMessage::new().ephemeral(true);
 
-	let content = {
-		let reminders = reminders.read().unwrap();
+	let response_message = match (rem_id, delet) {
+		(None, false) if export => {
+			let reminders = listed_reminders(db, command.user.id, &state);
+			let ics = ical::export(&reminders, date_time_now().timestamp());
 
-		let mut content = if let Some(rem_id) = rem_id {
-			let rem = (reminders.iter())
-				.find(|rem| rem.user_id == command.user.id && rem.id == rem_id)
-				.cloned();
-
-			if let Some(rem) = rem {
-				if delet {
-					rems_to_delet.insert(rem.id);
-				}
+			let mut content = match reminders.len() {
+				1 => "Here's your reminder, for your calendar app~".to_string(),
+				l => format!("Here are your {l} reminders, for your calendar app~"),
+			};
+			// exported in UTC, so calendar apps keep weekly ones at the same UTC time all year
+			if reminders
+				.iter()
+				.any(|r| matches!(r.recurrence, Some(Recurrence::Weekly(..))))
+			{
+				content += "\n-# Weekly ones may be an hour off in your calendar when daylight saving time changes :c";
+			}
 
-				format!(
-					"Here's your reminder~\n`{}` <t:{}:F> in <#{}>\n\n{}",
-					rem.id, rem.timestamp, rem.channel_id, rem.message
-				)
-			} else {
-				String::from("No such reminder :(")
+			(response_message.content(content)).add_file(CreateAttachment::bytes(ics.into_bytes(), "reminders.ics"))
+		}
+		(Some(rem_id), _) => match db.user_reminder(command.user.id, rem_id).unwrap() {
+			Some(rem) if delet => {
+				let (embed, components) = confirm_view(&[rem], command.user.id, tz, state, Target::One(rem_id));
+				response_message.embed(embed).components(components)
 			}
-		} else {
-			if delet {
-				rems_to_delet.extend(
-					(reminders.iter())
-						.filter(|rem| rem.user_id == command.user.id)
-						.map(|rem| rem.id),
-				);
+			Some(rem) => {
+				let (embed, components) = reminder_view(&rem, command.user.id, tz, state);
+				response_message.embed(embed).components(components)
 			}
+			None => response_message.content("No such reminder :("),
+		},
+		(None, true) => {
+			let reminders = listed_reminders(db, command.user.id, &state);
 
-			let reminders = (reminders.iter().take(41))
-				.filter(|rem| rem.user_id == command.user.id)
-				.collect::<Vec<_>>();
+			match reminders.iter().map(|rem| rem.id).max() {
+				Some(max_id) => {
+					let (embed, components) =
+						confirm_view(&reminders, command.user.id, tz, state, Target::All { max_id });
+					response_message.embed(embed).components(components)
+				}
+				None => response_message.content("You have no reminders! Sorry~"),
+			}
+		}
+		(None, false) => {
+			let (embed, components) = list_view(db, command.user.id, state);
+			response_message.embed(embed).components(components)
+		}
+	};
 
-			let mut content = if reminders.is_empty() {
-				String::from("You have no reminders! Sorry~")
-			} else if reminders.len() <= 40 {
-				String::from("Here are all your reminders~\n")
-			} else {
-				String::from("Wow, you have more than 40 reminders! Here are your oldest ones...\n")
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
+}
+
+pub async fn handle_component(db: &Db, ctx: &Context, component: &ComponentInteraction) {
+	let custom_id = &component.data.custom_id;
+	let Some((action, state, rest)) = ListState::parse(custom_id) else {
+		tracing::error!("Invalid reminder list component {custom_id:?}");
+		return;
+	};
+
+	let user_id = component.user.id;
+	let tz = user_timezone(db, user_id);
+
+	// the list is ephemeral, so only the user who asked for it can click around
+	let response_message = CreateInteractionResponseMessage::new().content("");
+
+	let response_message = match action {
+		"list" | "prev" | "next" => {
+			let (embed, components) = list_view(db, user_id, state);
+			response_message.embed(embed).components(components)
+		}
+		"open" => {
+			let rem_id = match &component.data.kind {
+				ComponentInteractionDataKind::StringSelect { values } => values.first().and_then(|id| id.parse().ok()),
+				_ => None,
 			};
 
-			for rem in reminders.iter().take(40) {
-				let message = if rem.message.len() <= 80 {
-					rem.message.clone()
-				} else {
-					format!("{}...", &rem.message[..37])
-				};
-
-				// replace backticks with grave accent to avoid breaking the display
-				// make sure no newlines or weird whitespace
-				let message = message.replace('`', "ˋ").replace(['\n', '\t'], " ");
-
-				write!(
-					&mut content,
-					"\n- `{}` <t:{}:F> in <#{}> `{}`",
-					rem.id, rem.timestamp, rem.channel_id, message
-				)
-				.unwrap();
+			match rem_id.and_then(|rem_id| db.user_reminder(user_id, rem_id).unwrap()) {
+				Some(rem) => {
+					let (embed, components) = reminder_view(&rem, user_id, tz, state);
+					response_message.embed(embed).components(components)
+				}
+				None => list_message(db, user_id, state, "No such reminder :( Was it already sent?"),
 			}
+		}
+		"delete" | "confirm" => {
+			let Some(target) = rest.and_then(Target::parse) else {
+				tracing::error!("Invalid reminder list component {custom_id:?}");
+				return;
+			};
 
-			content
-		};
+			let reminders = target.reminders(db, user_id, &state);
 
-		if delet {
-			content += &match rems_to_delet.len() {
-				1 => "\n\n1 reminder deleted!".to_string(),
-				l => format!("\n\n{l} reminders deleted!"),
-			};
+			if reminders.is_empty() {
+				list_message(db, user_id, state, "No such reminder :( Was it already sent?")
+			} else if action == "delete" {
+				let (embed, components) = confirm_view(&reminders, user_id, tz, state, target);
+				response_message.embed(embed).components(components)
+			} else {
+				delete_and_list(db, user_id, reminders, state)
+			}
 		}
+		"cancel" => list_message(db, user_id, state, "Okie, I didn't delete anything~"),
+		"undo" => {
+			let Some(batch) = rest.and_then(|batch| batch.parse().ok()) else {
+				tracing::error!("Invalid reminder list component {custom_id:?}");
+				return;
+			};
+
+			match take_deleted(user_id, batch, date_time_now().timestamp()) {
+				Some(reminders) => {
+					db.restore_reminders(user_id, &reminders).unwrap();
 
-		content
+					let content = match reminders.len() {
+						1 => "Okie, I put back 1 reminder~".to_string(),
+						l => format!("Okie, I put back {l} reminders~"),
+					};
+					list_message(db, user_id, state, &content)
+				}
+				None => list_message(db, user_id, state, "Too late, these are gone for good :c"),
+			}
+		}
+		action => {
+			tracing::error!("Invalid reminder list action {action:?}");
+			return;
+		}
 	};
 
-	if delet {
-		let mut reminders = reminders.write().unwrap();
+	let builder = CreateInteractionResponse::UpdateMessage(response_message);
+	if let Err(e) = component.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to reminder list component: {e}");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mentions::Mentions;
 
-		let new_reminders = (reminders.iter())
-			.filter(|rem| !rems_to_delet.contains(&rem.id))
-			.cloned()
-			.collect::<VecDeque<_>>();
+	#[test]
+	fn deleted_reminders_can_be_taken_back_once_in_time() {
+		let user = UserId::new(1);
+		let other = UserId::new(2);
 
-		*reminders = new_reminders;
+		let batch = remember_deleted(user, Vec::new(), 1000);
+		assert!(take_deleted(other, batch, 1000).is_none());
+		assert!(take_deleted(user, batch, 1000 + UNDO_WINDOW_SECS).is_some());
+		assert!(take_deleted(user, batch, 1000).is_none());
 
-		store_reminders(reminders.deref()).unwrap();
+		let batch = remember_deleted(user, Vec::new(), 1000);
+		assert!(take_deleted(user, batch, 1001 + UNDO_WINDOW_SECS).is_none());
 	}
 
-	let response_message = CreateInteractionResponseMessage::new().ephemeral(true).content(content);
-	let builder = CreateInteractionResponse::Message(response_message);
-	if let Err(e) = command.create_response(&ctx.http, builder).await {
-		tracing::error!("Cannot respond to slash command: {e}");
+	#[test]
+	fn undo_leaves_reminders_edited_since_alone() {
+		let db = Db::open(":memory:").unwrap();
+		let owner = UserId::new(1);
+		let user = UserId::new(2);
+
+		let mut rem = Reminder {
+			id: 0,
+			timestamp: 2000,
+			user_id: owner,
+			channel_id: ChannelId::new(3),
+			guild_id: None,
+			message: "stand-up".to_string(),
+			recurrence: None,
+			deliver: Delivery::Channel,
+			mentions: Mentions::default(),
+			subscribers: Vec::new(),
+			post: None,
+			retry: None,
+		};
+		rem.id = db.add_reminder(&rem).unwrap();
+		let owned = db
+			.add_reminder(&Reminder {
+				user_id: user,
+				..rem.clone()
+			})
+			.unwrap();
+		assert!(db.subscribe(rem.id, user, 20).unwrap());
+
+		// the user leaves the owner's reminder and deletes their own, then the owner edits theirs
+		let left = db.user_reminders(user).unwrap();
+		assert_eq!(db.delete_user_reminders(user, &[rem.id, owned]).unwrap(), 2);
+		db.update_reminder(&Reminder {
+			timestamp: 3000,
+			message: "stand-up, moved".to_string(),
+			..rem.clone()
+		})
+		.unwrap();
+
+		db.restore_reminders(user, &left).unwrap();
+
+		let restored = db.user_reminders(user).unwrap();
+		assert_eq!(restored.len(), 2);
+		let joined = restored.iter().find(|r| r.id == rem.id).unwrap();
+		assert_eq!((joined.timestamp, joined.message.as_str()), (3000, "stand-up, moved"));
+		assert_eq!(joined.subscribers, [user]);
+		assert!(restored.iter().any(|r| r.id == owned && r.user_id == user));
+
+		// reminders gone since then stay gone
+		db.delete_user_reminders(owner, &[rem.id]).unwrap();
+		db.restore_reminders(user, &left).unwrap();
+		assert!(db.user_reminder(owner, rem.id).unwrap().is_none());
+	}
+
+	#[test]
+	fn delete_targets_round_trip() {
+		for target in [Target::One(42), Target::All { max_id: 7 }] {
+			assert_eq!(Target::parse(&target.to_string()), Some(target));
+		}
+		assert_eq!(Target::parse("all-"), None);
 	}
 }
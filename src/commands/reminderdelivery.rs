@@ -0,0 +1,55 @@
+use serenity::all::{
+	CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+	CreateInteractionResponseMessage,
+};
+
+use crate::db::Db;
+use crate::delivery::{user_delivery, Delivery};
+
+pub const NAME: &str = "reminderdelivery";
+pub const DESCRIPTION: &str = "Choose where I send your reminders when you don't tell me~";
+
+/// The `deliver` option, shared with the commands that create reminders.
+pub fn deliver_option(description: &str) -> CreateCommandOption {
+	CreateCommandOption::new(CommandOptionType::String, "deliver", description)
+		.add_string_choice("In the channel", "channel")
+		.add_string_choice("By DM", "dm")
+		.add_string_choice("Both", "both")
+}
+
+pub fn register() -> CreateCommand {
+	CreateCommand::new(NAME)
+		.description(DESCRIPTION)
+		.add_option(deliver_option(
+			"Where your reminders go by default (shows the current one if not specified)",
+		))
+}
+
+pub async fn run(db: &Db, ctx: &Context, command: &CommandInteraction) {
+	let mut deliver = None;
+
+	for option in &command.data.options {
+		match option.name.as_str() {
+			"deliver" => deliver = option.value.as_str().and_then(Delivery::from_option),
+			s => tracing::error!("Invalid option {s:?}"),
+		}
+	}
+
+	let content = match deliver {
+		Some(deliver) => {
+			db.set_delivery(command.user.id, deliver).unwrap();
+
+			format!("Okie, I'll send your reminders {deliver} from now on~")
+		}
+		None => format!(
+			"I send your reminders {} unless you tell me otherwise~",
+			user_delivery(db, command.user.id)
+		),
+	};
+
+	let response_message = CreateInteractionResponseMessage::new().ephemeral(true).content(content);
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
+}
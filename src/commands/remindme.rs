@@ -1,17 +1,23 @@
-use std::{
-	collections::VecDeque,
-	num::ParseIntError,
-	ops::Deref,
-	sync::{atomic::Ordering, Arc, RwLock},
-};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
-use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use chrono::TimeZone;
 use serenity::all::{
-	CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, CreateInteractionResponse,
-	CreateInteractionResponseMessage,
+	Attachment, ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, Context, CreateActionRow,
+	CreateAllowedMentions, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+	CreateInteractionResponse, CreateInteractionResponseMessage, UserId,
 };
 
-use crate::reminders::{date_time_now, store_reminders, Reminder, NEXT_REMINDER_ID};
+use crate::commands::reminderdelivery::deliver_option;
+use crate::db::Db;
+use crate::delivery::{user_delivery, Delivery};
+use crate::group;
+use crate::ical;
+use crate::limits::{self, Limits};
+use crate::mentions::{permitted_mentions, Mentions};
+use crate::reminders::{date_time_now, excerpt, Reminder};
+use crate::time_expr::{error_message, parse_recurrence, parse_time_expr};
+use crate::timezones::{format_in_timezone, user_timezone};
 
 pub const NAME: &str = "remindme";
 pub const DESCRIPTION: &str = "I'll remind you whatever you want later~ ♡";
@@ -19,108 +25,182 @@ pub const DESCRIPTION: &str = "I'll remind you whatever you want later~ ♡";
 pub fn register() -> CreateCommand {
 	CreateCommand::new(NAME)
 		.description(DESCRIPTION)
-		.add_option(
-			CreateCommandOption::new(
-				CommandOptionType::String,
-				"time",
-				"Duration like 1d, 3h 10m, 5s, or specific date (UTC) like 2027-06-10 12:23:00",
-			)
-			.required(true),
-		)
-		.add_option(
-			CreateCommandOption::new(CommandOptionType::String, "message", "Content of the reminder").required(true),
-		)
+		// not required so that calendars can be imported instead
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::String,
+			"message",
+			"Content of the reminder",
+		))
+		// optional so that repeat rules like "every monday 09:00" don't need a separate first time
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::String,
+			"time",
+			"Like 3h 10m, tomorrow at 9, next friday, noon, or 2027-06-10 12:23 (see /timezone)",
+		))
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::String,
+			"repeat",
+			"Repeat the reminder, like every 1d, every 12h, or every monday 09:00",
+		))
+		.add_option(deliver_option(
+			"Where to send the reminder (see /reminderdelivery for the default)",
+		))
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::String,
+			"ping",
+			"Who else to ping, like @someone @some-role or @here",
+		))
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::Boolean,
+			"public",
+			"Add a \"Remind me too\" button so others can join",
+		))
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::Attachment,
+			"import",
+			"A calendar file (.ics) to make reminders from its events instead",
+		))
 }
 
-const WRONG: &str = r#"
+pub const WRONG: &str = r#"
+
+Valid times include:
+- durations like `1d 3h 10m`, `in 2 weeks`, `in an hour`, `35hrs 4min`
+  - units: `s` `m` `h` `d` `w` `mo` `y`, and their longer names like `min(s)` `hour(s)` `week(s)` `month(s)`
+- days like `today`, `tomorrow`, `friday`, `next monday`, `next week`, or a date like `2027-06-10`
+- times of day like `at 9`, `9pm`, `17:30`, `12:23:45`, `noon`, `midnight` or `eod` (17:00)
+- days and times together, like `tomorrow at 9`, `next friday 17:30` or `2027-06-10 12:23`
+
+Dates and times are in your time zone (UTC unless you changed it with `/timezone set`)."#;
 
-Valid time formats include:
-- a duration
-  - valid suffixes: `d` `h` `m` `s`, `hr(s)` `min(s)` `sec(s)`, `day(s)` `hour(s)` `minute(s)` `second(s)`
-  - duration format: `<number><suffix> <number><suffix> <number><suffix> ...` (no space between number and suffix)
-  - examples: `1d 3h 10m`, `23day`, `35hrs 4min`, `727secs`
-- a UTC date
-  - valid formats: `YYYY-MM-DD`, `YYYY-MM-DD hh:mm`, `YYYY-MM-DD hh:mm:ss`"#;
+pub const WRONG_REPEAT: &str = r#"
 
-pub async fn run(reminders: Arc<RwLock<VecDeque<Reminder>>>, ctx: &Context, command: &CommandInteraction) {
+Valid repeat formats include:
+- `every <duration>`, like `every day`, `every 12h 30m` (at least one minute)
+- `every <weekday>` or `every <weekday> at <time>`, like `every monday 09:00` (in your time zone)"#;
+
+pub async fn run(db: &Db, limits: &Limits, ctx: &Context, command: &CommandInteraction) {
 	let now = date_time_now();
+	let tz = user_timezone(db, command.user.id);
 
 	let mut time = None;
 	let mut message = None;
+	let mut repeat = None;
+	let mut deliver = None;
+	let mut ping = None;
+	let mut public = false;
+	let mut import = None;
 
 	for option in &command.data.options {
 		match option.name.as_str() {
 			"time" => time = Some(option.value.as_str().unwrap()),
 			"message" => message = Some(option.value.as_str().unwrap()),
+			"repeat" => repeat = Some(option.value.as_str().unwrap()),
+			"deliver" => deliver = option.value.as_str().and_then(Delivery::from_option),
+			"ping" => ping = option.value.as_str(),
+			"public" => public = option.value.as_bool().unwrap_or_default(),
+			"import" => import = option.value.as_attachment_id(),
 			s => tracing::error!("Invalid option {s:?}"),
 		}
 	}
 
-	let time = time.unwrap();
-	let message = message.unwrap().to_string();
+	let deliver = deliver.unwrap_or_else(|| user_delivery(db, command.user.id));
+
+	if let Some(attachment) = import.and_then(|id| command.data.resolved.attachments.get(&id)) {
+		run_import(db, limits, ctx, command, attachment, deliver).await;
+		return;
+	}
+
+	let Some(message) = message.map(str::to_string) else {
+		let builder = CreateInteractionResponse::Message(
+			CreateInteractionResponseMessage::new()
+				.content("What should I remind you of? Give me a `message`, or a calendar file to `import`~"),
+		);
+		if let Err(e) = command.create_response(&ctx.http, builder).await {
+			tracing::error!("Cannot respond to slash command: {e}");
+		}
+		return;
+	};
+
+	let mentions = match check_audience(ctx, command, ping.unwrap_or_default(), deliver, public).await {
+		Ok(mentions) => mentions,
+		Err(content) => {
+			let builder = CreateInteractionResponse::Message(
+				CreateInteractionResponseMessage::new()
+					.content(content)
+					.allowed_mentions(CreateAllowedMentions::new()),
+			);
+			if let Err(e) = command.create_response(&ctx.http, builder).await {
+				tracing::error!("Cannot respond to slash command: {e}");
+			}
+			return;
+		}
+	};
+
+	let recurrence = match repeat.map(|repeat| (repeat, parse_recurrence(repeat))) {
+		None => None,
+		Some((_, Ok(recurrence))) => Some(recurrence),
+		Some((repeat, Err(e))) => {
+			let builder = CreateInteractionResponse::Message(
+				CreateInteractionResponseMessage::new().content(error_message(&e, repeat) + WRONG_REPEAT),
+			);
+			if let Err(e) = command.create_response(&ctx.http, builder).await {
+				tracing::error!("Cannot respond to slash command: {e}");
+			}
+			return;
+		}
+	};
 
 	let timestamp;
 	let mut content;
 
-	if let Some(date_time) = parse_time_delta(&now, time) {
-		let ts = date_time.timestamp();
-		timestamp = Some(ts);
-
-		content = format!("Okie, will remind you <t:{ts}:R> ~");
-	} else {
-		match parse_date_time(&now, time) {
+	match (time, recurrence) {
+		(Some(time), _) => match parse_time_expr(&tz.from_utc_datetime(&now.naive_utc()), time) {
 			Ok(date_time) => {
 				let ts = date_time.timestamp();
 				timestamp = Some(ts);
 
-				content = format!("Okie, will remind you on <t:{ts}:F> ~");
+				content = format!("Okie, will remind you <t:{ts}:R> (on {})", format_in_timezone(ts, tz));
 			}
 			Err(e) => {
 				timestamp = None;
 
-				content = match e {
-					ParseDateTimeError::UnrecognizedDateFormat => {
-						"I don't recognize this date format! I only know `YYYY-MM-DD`.".to_string()
-					}
-					ParseDateTimeError::UnrecognizedTimeFormat => {
-						"I don't recognize this time format! I only know `hh:mm` and `hh:mm:ss`.".to_string()
-					}
-					ParseDateTimeError::ParseYear(pie) => {
-						format!("Was that a number for the year? I don't get it :c\n`{pie}`")
-					}
-					ParseDateTimeError::ParseMonth(pie) => {
-						format!("Was that a number for the month? I don't get it :c\n`{pie}`")
-					}
-					ParseDateTimeError::ParseDay(pie) => {
-						format!("Was that a number for the day? I don't get it :c\n`{pie}`")
-					}
-					ParseDateTimeError::InvalidDate => "This date is invalid!".to_string(),
-					ParseDateTimeError::InvalidMonth => {
-						"This month is invalid! There is no more of them after December~".to_string()
-					}
-					ParseDateTimeError::InvalidDay => {
-						"This day is invalid! There are never more than 31 days~".to_string()
-					}
-					ParseDateTimeError::ParseHour(pie) => {
-						format!("Was that a number for the hours? I don't get it :c\n`{pie}`")
-					}
-					ParseDateTimeError::ParseMin(pie) => {
-						format!("Was that a number for the minutes? I don't get it :c\n`{pie}`")
-					}
-					ParseDateTimeError::ParseSec(pie) => {
-						format!("Was that a number for the seconds? I don't get it :c\n`{pie}`")
-					}
-					ParseDateTimeError::InvalidHour => {
-						"This hour is invalid! I don't know how to count after 23, tehe :P".to_string()
-					}
-					ParseDateTimeError::InvalidMin => "This minute is invalid!".to_string(),
-					ParseDateTimeError::InvalidSec => "This second is invalid!".to_string(),
-				};
-
-				content += WRONG;
+				content = error_message(&e, time) + WRONG;
 			}
-		};
-	};
+		},
+		(None, Some(recurrence)) => {
+			// no time given: start at the first occurrence of the repeat rule
+			timestamp = recurrence.next_after(now.timestamp(), now.timestamp(), tz);
+
+			content = match timestamp {
+				Some(ts) => format!("Okie, will remind you <t:{ts}:R> (on {})", format_in_timezone(ts, tz)),
+				None => "That's way too far away, even for me!".to_string(),
+			};
+		}
+		(None, None) => {
+			timestamp = None;
+
+			content = "When should I remind you? Give me a `time`, a `repeat` rule, or both~".to_string();
+		}
+	}
+
+	if timestamp.is_some() {
+		if let Some(recurrence) = recurrence {
+			content += &format!(", and then {recurrence}");
+		}
+
+		if deliver != Delivery::Channel {
+			content += &format!(", {deliver}");
+		}
+
+		if !mentions.is_empty() {
+			content += &format!(", pinging {mentions}");
+		}
+
+		content += " ~";
+	}
+
+	let mut components = Vec::new();
 
 	'remind_store: {
 		if let Some(timestamp) = timestamp {
@@ -132,138 +212,295 @@ pub async fn run(reminders: Arc<RwLock<VecDeque<Reminder>>>, ctx: &Context, comm
 			let user_id = command.user.id;
 			let channel_id = command.channel_id;
 
-			let mut reminders = reminders.write().unwrap();
-
-			let idx = match reminders.binary_search_by(|r| r.timestamp.cmp(&timestamp)) {
-				Ok(idx) => idx,
-				Err(idx) => idx,
+			let reminder = Reminder {
+				id: 0,
+				timestamp,
+				user_id,
+				channel_id,
+				guild_id: command.guild_id,
+				message,
+				recurrence,
+				deliver,
+				mentions,
+				subscribers: Vec::new(),
+				post: None,
+				retry: None,
 			};
 
-			let id = NEXT_REMINDER_ID.load(Ordering::Relaxed);
-			NEXT_REMINDER_ID.store(id + 1, Ordering::Relaxed);
-
-			reminders.insert(
-				idx,
-				Reminder {
-					id,
-					timestamp,
-					user_id,
-					channel_id,
-					message,
-				},
-			);
+			if let Err(e) = limits.check_new(db, std::slice::from_ref(&reminder), now.timestamp()) {
+				content = limits::error_message(&e);
+				break 'remind_store;
+			}
 
-			store_reminders(reminders.deref()).unwrap();
+			let id = db.add_reminder(&reminder).unwrap();
+
+			if public {
+				content += "\n-# Want it too? Press the button~";
+				components = group::buttons(id);
+			}
 		}
 	}
 
-	let builder = CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(content));
+	// the confirmation shows who will be pinged, without pinging them yet
+	let builder = CreateInteractionResponse::Message(
+		CreateInteractionResponseMessage::new()
+			.content(content)
+			.allowed_mentions(CreateAllowedMentions::new())
+			.components(components),
+	);
 	if let Err(e) = command.create_response(&ctx.http, builder).await {
 		tracing::error!("Cannot respond to slash command: {e}");
 	}
 }
 
-enum ParseDateTimeError {
-	UnrecognizedDateFormat,
-	UnrecognizedTimeFormat,
+/// Parses the `ping` option, and makes sure everyone the reminder is for can get it:
+/// the user has to be allowed to ping all of it in the channel, and group reminders can't go to DMs only.
+async fn check_audience(
+	ctx: &Context,
+	command: &CommandInteraction,
+	ping: &str,
+	deliver: Delivery,
+	public: bool,
+) -> Result<Mentions, String> {
+	if public && deliver == Delivery::Dm {
+		return Err("Group reminders are sent in the channel, so everyone who joins gets them~".to_string());
+	}
+
+	let mut mentions = Mentions::parse(ping)
+		.map_err(|word| format!("I don't know who `{word}` is :c Ping people like @someone, @some-role or @here~"))?;
+
+	// they get pinged anyway
+	mentions.users.retain(|&user| user != command.user.id);
+
+	if mentions.is_empty() {
+		return Ok(mentions);
+	}
+
+	if deliver == Delivery::Dm {
+		return Err("I can only ping others in the channel, not in your DMs~".to_string());
+	}
+
+	let permitted = permitted_mentions(ctx, command.user.id, command.channel_id, &mentions).await;
+	let forbidden = mentions.without(&permitted);
+	if !forbidden.is_empty() {
+		return Err(format!("Sorry, you're not allowed to ping {forbidden} in here :c"));
+	}
+
+	Ok(mentions)
+}
+
+/// Prefix of the custom IDs of the buttons to confirm an import, followed by `{action}:{import id}`.
+pub const IMPORT_ID_PREFIX: &str = "remindme-import:";
 
-	ParseYear(ParseIntError),
-	ParseMonth(ParseIntError),
-	ParseDay(ParseIntError),
+/// Largest calendar file accepted.
+const MAX_IMPORT_BYTES: u32 = 1 << 20;
 
-	InvalidDate,
+/// Most reminders made from a single calendar.
+const MAX_IMPORTED_EVENTS: usize = 100;
 
-	InvalidMonth,
-	InvalidDay,
+/// How many of the reminders to be imported the preview lists.
+const PREVIEW_MAX_LINES: usize = 20;
 
-	ParseHour(ParseIntError),
-	ParseMin(ParseIntError),
-	ParseSec(ParseIntError),
+/// How long an import waits to be confirmed.
+const IMPORT_EXPIRY_SECS: i64 = 15 * 60;
 
-	InvalidHour,
-	InvalidMin,
-	InvalidSec,
+/// Reminders read from a calendar, waiting for the user to confirm the import.
+struct PendingImport {
+	id: u64,
+	user_id: UserId,
+	created_at: i64,
+	reminders: Vec<Reminder>,
 }
 
-fn parse_date_time(now: &DateTime<Utc>, input: &str) -> Result<DateTime<Utc>, ParseDateTimeError> {
-	let input = input.trim();
-	let (date_parts, naive_time) = if let Some((date_part, time_part)) = input.split_once(' ') {
-		let time_part = time_part.trim().split(':').collect::<Vec<_>>();
-
-		let (hour, min, sec) = if let &[h, m, s] = time_part.as_slice() {
-			(h, m, s)
-		} else if let &[h, m] = time_part.as_slice() {
-			(h, m, "0")
-		} else {
-			return Err(ParseDateTimeError::UnrecognizedTimeFormat);
+static NEXT_IMPORT_ID: AtomicU64 = AtomicU64::new(1);
+static PENDING_IMPORTS: Mutex<Vec<PendingImport>> = Mutex::new(Vec::new());
+
+/// Reads the events of a calendar file and shows what reminders they'd make, with buttons to confirm.
+async fn run_import(
+	db: &Db,
+	limits: &Limits,
+	ctx: &Context,
+	command: &CommandInteraction,
+	attachment: &Attachment,
+	deliver: Delivery,
+) {
+	let now = date_time_now().timestamp();
+	let tz = user_timezone(db, command.user.id);
+
+	let response_message = CreateInteractionResponseMessage::new().ephemeral(true);
+
+	let response_message = 'response: {
+		if attachment.size > MAX_IMPORT_BYTES {
+			break 'response response_message.content("That file is too big for me :c");
+		}
+
+		let text = match attachment.download().await {
+			Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+			Err(e) => {
+				tracing::error!("Cannot download calendar {}: {e}", attachment.url);
+				break 'response response_message.content("I couldn't download your file :c");
+			}
+		};
+
+		let mut imported = match ical::import(&text, tz, now) {
+			Ok(imported) => imported,
+			Err(e) => break 'response response_message.content(e),
 		};
 
-		let hour: u32 = hour.parse().map_err(ParseDateTimeError::ParseHour)?;
-		let min: u32 = min.parse().map_err(ParseDateTimeError::ParseMin)?;
-		let sec: u32 = sec.parse().map_err(ParseDateTimeError::ParseSec)?;
+		let to_reminder = |event: ical::IcsEvent| Reminder {
+			id: 0,
+			timestamp: event.timestamp,
+			user_id: command.user.id,
+			channel_id: command.channel_id,
+			guild_id: command.guild_id,
+			message: event.message,
+			recurrence: event.recurrence,
+			deliver,
+			mentions: Mentions::default(),
+			subscribers: Vec::new(),
+			post: None,
+			retry: None,
+		};
+
+		// leave out what /remindme wouldn't take, rather than refusing the whole file
+		let event_count = imported.events.len();
+		(imported.events).retain(|event| limits.check_content(&to_reminder(event.clone()), now).is_ok());
+		let refused = event_count - imported.events.len();
 
-		if hour >= 24 {
-			return Err(ParseDateTimeError::InvalidHour);
+		let mut notes = Vec::new();
+		if imported.past > 0 {
+			notes.push(format!("{} events already over", imported.past));
+		}
+		if imported.invalid > 0 {
+			notes.push(format!("{} events I couldn't read", imported.invalid));
 		}
-		if min >= 60 {
-			return Err(ParseDateTimeError::InvalidMin);
+		if refused > 0 {
+			notes.push(format!("{refused} events too far away or too long"));
 		}
-		if sec >= 60 {
-			return Err(ParseDateTimeError::InvalidSec);
+		if imported.events.len() > MAX_IMPORTED_EVENTS {
+			notes.push(format!(
+				"{} events past the first {MAX_IMPORTED_EVENTS}",
+				imported.events.len() - MAX_IMPORTED_EVENTS
+			));
 		}
 
-		let naive_time = NaiveTime::from_hms_opt(hour, min, sec).unwrap();
+		if imported.events.is_empty() {
+			break 'response response_message.content("I didn't find any upcoming events in there :c");
+		}
 
-		let date_parts = date_part.trim().split('-').collect::<Vec<_>>();
-		(date_parts, naive_time)
-	} else {
-		let naive_time = now.naive_utc().time();
+		let mut description = String::new();
+		for event in imported.events.iter().take(PREVIEW_MAX_LINES) {
+			// replace backticks with grave accent to avoid breaking the display
+			let message = excerpt(&event.message, 60).replace('`', "ˋ");
+			description += &format!("- <t:{}:f> `{message}`", event.timestamp);
 
-		let date_parts = input.split('-').collect::<Vec<_>>();
-		(date_parts, naive_time)
-	};
+			if let Some(recurrence) = event.recurrence {
+				description += &format!(" (repeats {recurrence})");
+			} else if event.unsupported_repeat {
+				description += " (only once, I don't understand its repeat rule)";
+			}
+			description += "\n";
+		}
 
-	let [year, month, day] = date_parts.as_slice() else {
-		return Err(ParseDateTimeError::UnrecognizedDateFormat);
-	};
+		let count = imported.events.len().min(MAX_IMPORTED_EVENTS);
+		if count > PREVIEW_MAX_LINES {
+			description += &format!("...and {} more", count - PREVIEW_MAX_LINES);
+		}
 
-	let year: i32 = year.parse().map_err(ParseDateTimeError::ParseYear)?;
-	let month: u32 = month.parse().map_err(ParseDateTimeError::ParseMonth)?;
-	let day: u32 = day.parse().map_err(ParseDateTimeError::ParseDay)?;
+		let reminders = (imported.events.into_iter().take(MAX_IMPORTED_EVENTS))
+			.map(to_reminder)
+			.collect();
+
+		let import_id = NEXT_IMPORT_ID.fetch_add(1, Ordering::Relaxed);
+		{
+			let mut pending = PENDING_IMPORTS.lock().unwrap();
+			pending.retain(|import| now - import.created_at <= IMPORT_EXPIRY_SECS);
+			pending.push(PendingImport {
+				id: import_id,
+				user_id: command.user.id,
+				created_at: now,
+				reminders,
+			});
+		}
 
-	if month > 12 {
-		return Err(ParseDateTimeError::InvalidMonth);
-	}
-	if day > 31 {
-		return Err(ParseDateTimeError::InvalidDay);
-	}
+		let title = match count {
+			1 => "I'll make this reminder~".to_string(),
+			count => format!("I'll make these {count} reminders~"),
+		};
 
-	let naive_date = NaiveDate::from_ymd_opt(year, month, day).ok_or(ParseDateTimeError::InvalidDate)?;
+		let mut embed = CreateEmbed::new().title(title).description(description);
+		if !notes.is_empty() {
+			embed = embed.footer(CreateEmbedFooter::new(format!("Skipping {}", notes.join(", "))));
+		}
 
-	Ok(NaiveDateTime::new(naive_date, naive_time).and_utc())
+		let buttons = vec![
+			CreateButton::new(format!("{IMPORT_ID_PREFIX}confirm:{import_id}"))
+				.label("Make them!")
+				.style(ButtonStyle::Success),
+			CreateButton::new(format!("{IMPORT_ID_PREFIX}cancel:{import_id}"))
+				.label("Cancel")
+				.style(ButtonStyle::Secondary),
+		];
+
+		response_message
+			.embed(embed)
+			.components(vec![CreateActionRow::Buttons(buttons)])
+	};
+
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
 }
 
-fn parse_time_delta(now: &DateTime<Utc>, input: &str) -> Option<DateTime<Utc>> {
-	let mut time_delta = chrono::TimeDelta::zero();
+pub async fn handle_import_component(db: &Db, limits: &Limits, ctx: &Context, component: &ComponentInteraction) {
+	let custom_id = &component.data.custom_id;
+	let Some((action, import_id)) = (custom_id.strip_prefix(IMPORT_ID_PREFIX))
+		.and_then(|rest| rest.split_once(':'))
+		.and_then(|(action, id)| Some((action, id.parse::<u64>().ok()?)))
+	else {
+		tracing::error!("Invalid import button {custom_id:?}");
+		return;
+	};
 
-	for input_part in input.trim().split(' ') {
-		let input_part = input_part.trim();
-		if input_part.is_empty() {
-			continue;
-		}
+	let now = date_time_now().timestamp();
+	let import = {
+		let mut pending = PENDING_IMPORTS.lock().unwrap();
+		(pending.iter())
+			.position(|import| import.id == import_id && import.user_id == component.user.id)
+			.map(|index| pending.swap_remove(index))
+			.filter(|import| now - import.created_at <= IMPORT_EXPIRY_SECS)
+	};
 
-		let digits = input_part.chars().take_while(char::is_ascii_digit).collect::<String>();
-		let n_len = digits.len();
-		let n: i64 = digits.parse().ok()?;
+	let content = match (action, import) {
+		(_, None) => "This import expired, try again~".to_string(),
+		("confirm", Some(import)) => 'confirm: {
+			if let Err(e) = limits.check_new(db, &import.reminders, now) {
+				break 'confirm limits::error_message(&e);
+			}
+
+			for reminder in &import.reminders {
+				db.add_reminder(reminder).unwrap();
+			}
 
-		match input_part[n_len..].trim() {
-			"d" | "day" | "days" => time_delta += TimeDelta::days(n),
-			"h" | "hr" | "hrs" | "hour" | "hours" => time_delta += TimeDelta::hours(n),
-			"m" | "min" | "mins" | "minute" | "minutes" => time_delta += TimeDelta::minutes(n),
-			"s" | "sec" | "secs" | "second" | "seconds" => time_delta += TimeDelta::seconds(n),
-			_ => return None,
+			match import.reminders.len() {
+				1 => "Okie, I made 1 reminder~ ♡".to_string(),
+				count => format!("Okie, I made {count} reminders~ ♡"),
+			}
 		}
-	}
+		("cancel", Some(_)) => "Okie, I won't import anything~".to_string(),
+		(action, Some(_)) => {
+			tracing::error!("Invalid import action {action:?}");
+			return;
+		}
+	};
 
-	now.checked_add_signed(time_delta)
+	let response_message = CreateInteractionResponseMessage::new()
+		.content(content)
+		.embeds(vec![])
+		.components(vec![]);
+	let builder = CreateInteractionResponse::UpdateMessage(response_message);
+	if let Err(e) = component.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to button: {e}");
+	}
 }
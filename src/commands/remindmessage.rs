@@ -0,0 +1,180 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use chrono::TimeZone;
+use serenity::all::{
+	CommandInteraction, CommandType, Context, CreateActionRow, CreateCommand, CreateInputText,
+	CreateInteractionResponse, CreateInteractionResponseMessage, CreateModal, InputTextStyle, ModalInteraction,
+	ResolvedTarget, UserId,
+};
+
+use crate::commands::input_value;
+use crate::commands::remindme::WRONG;
+use crate::db::Db;
+use crate::delivery::user_delivery;
+use crate::limits::{self, Limits};
+use crate::mentions::Mentions;
+use crate::reminders::{date_time_now, Reminder};
+use crate::time_expr::{error_message, parse_time_expr};
+use crate::timezones::{format_in_timezone, user_timezone};
+
+pub const NAME: &str = "Remind me about this message";
+
+/// Prefix of the custom ID of the time modal, followed by the ID of its [`PendingMessage`].
+pub const MODAL_ID_PREFIX: &str = "remindmessage:";
+
+/// How many characters of the target message get quoted in the reminder.
+const EXCERPT_LEN: usize = 200;
+
+/// How long the time modal can stay open.
+const MODAL_EXPIRY_SECS: i64 = 15 * 60;
+
+/// The message a user is picking a reminder time for, taken when they used the command,
+/// as fetching it again would need Read Message History, which AI-chan doesn't ask for.
+struct PendingMessage {
+	id: u64,
+	user_id: UserId,
+	created_at: i64,
+	/// Who said what, quoted.
+	quote: String,
+	link: String,
+}
+
+static NEXT_PENDING_ID: AtomicU64 = AtomicU64::new(0);
+static PENDING_MESSAGES: Mutex<Vec<PendingMessage>> = Mutex::new(Vec::new());
+
+pub fn register() -> CreateCommand {
+	CreateCommand::new(NAME).kind(CommandType::Message)
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) {
+	let Some(ResolvedTarget::Message(message)) = command.data.target() else {
+		tracing::error!("Message command called without a message");
+		return;
+	};
+
+	let time = CreateInputText::new(InputTextStyle::Short, "When?", "time")
+		.placeholder("tomorrow at 9, in 3h, 2027-06-10 12:23...");
+	let note = CreateInputText::new(InputTextStyle::Paragraph, "Note", "note")
+		.placeholder("Anything you want to remember about it~")
+		.max_length(1000)
+		.required(false);
+
+	let now = date_time_now().timestamp();
+	let pending_id = NEXT_PENDING_ID.fetch_add(1, Ordering::Relaxed);
+	{
+		let mut pending = PENDING_MESSAGES.lock().unwrap();
+		pending.retain(|pending| now - pending.created_at <= MODAL_EXPIRY_SECS);
+		pending.push(PendingMessage {
+			id: pending_id,
+			user_id: command.user.id,
+			created_at: now,
+			quote: format!("*{} said:*\n{}", message.author.name, quote(&message.content)),
+			link: message.id.link(command.channel_id, command.guild_id),
+		});
+	}
+
+	let modal = CreateModal::new(format!("{MODAL_ID_PREFIX}{pending_id}"), "Remind me about this message")
+		.components(vec![CreateActionRow::InputText(time), CreateActionRow::InputText(note)]);
+
+	let builder = CreateInteractionResponse::Modal(modal);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to message command: {e}");
+	}
+}
+
+pub async fn handle_modal(db: &Db, limits: &Limits, ctx: &Context, modal: &ModalInteraction) {
+	let custom_id = &modal.data.custom_id;
+	let Some(pending_id) = (custom_id.strip_prefix(MODAL_ID_PREFIX)).and_then(|id| id.parse::<u64>().ok()) else {
+		tracing::error!("Invalid message reminder modal {custom_id:?}");
+		return;
+	};
+
+	let now = date_time_now();
+	let pending = {
+		let mut pending = PENDING_MESSAGES.lock().unwrap();
+		(pending.iter())
+			.position(|pending| pending.id == pending_id && pending.user_id == modal.user.id)
+			.map(|index| pending.swap_remove(index))
+			.filter(|pending| now.timestamp() - pending.created_at <= MODAL_EXPIRY_SECS)
+	};
+	let tz = user_timezone(db, modal.user.id);
+
+	let time = input_value(modal, "time").unwrap_or_default().trim();
+	let note = input_value(modal, "note").unwrap_or_default().trim();
+
+	let content = 'content: {
+		let Some(pending) = pending else {
+			break 'content "This took too long, try again~".to_string();
+		};
+
+		match parse_time_expr(&tz.from_utc_datetime(&now.naive_utc()), time) {
+			Ok(date_time) if date_time.timestamp() <= now.timestamp() => {
+				"Sweetie, I don't have a time machine! :c".to_string()
+			}
+			Ok(date_time) => {
+				let timestamp = date_time.timestamp();
+				let link = pending.link;
+
+				let mut message = String::new();
+				if !note.is_empty() {
+					message += note;
+					message += "\n\n";
+				}
+
+				message += &format!("{}\n-# [Jump to message]({link})", pending.quote);
+
+				let reminder = Reminder {
+					id: 0,
+					timestamp,
+					user_id: modal.user.id,
+					channel_id: modal.channel_id,
+					guild_id: modal.guild_id,
+					message,
+					recurrence: None,
+					deliver: user_delivery(db, modal.user.id),
+					mentions: Mentions::default(),
+					subscribers: Vec::new(),
+					post: None,
+					retry: None,
+				};
+
+				if let Err(e) = limits.check_new(db, std::slice::from_ref(&reminder), now.timestamp()) {
+					break 'content limits::error_message(&e);
+				}
+
+				db.add_reminder(&reminder).unwrap();
+
+				format!(
+					"Okie, will remind you about {link} <t:{timestamp}:R> (on {}) ~",
+					format_in_timezone(timestamp, tz)
+				)
+			}
+			Err(e) => error_message(&e, time) + WRONG,
+		}
+	};
+
+	let response_message = CreateInteractionResponseMessage::new().ephemeral(true).content(content);
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = modal.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to modal: {e}");
+	}
+}
+
+/// Quotes the start of a message, cut on a character boundary.
+fn quote(content: &str) -> String {
+	let mut excerpt = content.chars().take(EXCERPT_LEN).collect::<String>();
+	if excerpt.len() < content.len() {
+		excerpt += "...";
+	}
+
+	if excerpt.trim().is_empty() {
+		return "> *(no text)*".to_string();
+	}
+
+	excerpt
+		.lines()
+		.map(|line| format!("> {line}"))
+		.collect::<Vec<_>>()
+		.join("\n")
+}
@@ -0,0 +1,196 @@
+use chrono::TimeZone;
+use serenity::all::{
+	ChannelType, CommandInteraction, CommandOptionType, Context, CreateAllowedMentions, CreateCommand,
+	CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+use crate::commands::remindme::{WRONG, WRONG_REPEAT};
+use crate::db::Db;
+use crate::delivery::Delivery;
+use crate::limits::{self, Limits};
+use crate::mentions::{permitted_mentions, Mentions};
+use crate::reminders::{date_time_now, Post, Reminder};
+use crate::time_expr::{error_message, parse_recurrence, parse_time_expr};
+use crate::timezones::{format_in_timezone, user_timezone};
+
+pub const NAME: &str = "schedule";
+pub const DESCRIPTION: &str = "I'll post a message in a channel later, no questions asked~";
+
+pub fn register() -> CreateCommand {
+	CreateCommand::new(NAME)
+		.description(DESCRIPTION)
+		.contexts(vec![InteractionContext::Guild])
+		.default_member_permissions(Permissions::MANAGE_MESSAGES)
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::Channel, "channel", "Where to post it")
+				.channel_types(vec![
+					ChannelType::Text,
+					ChannelType::News,
+					ChannelType::PublicThread,
+					ChannelType::PrivateThread,
+					ChannelType::NewsThread,
+				])
+				.required(true),
+		)
+		.add_option(
+			CreateCommandOption::new(
+				CommandOptionType::String,
+				"time",
+				"Like 3h 10m, tomorrow at 9, next friday, noon, or 2027-06-10 12:23 (see /timezone)",
+			)
+			.required(true),
+		)
+		.add_option(CreateCommandOption::new(CommandOptionType::String, "content", "What to post").max_length(2000))
+		.add_option(
+			CreateCommandOption::new(
+				CommandOptionType::String,
+				"embed_title",
+				"Title of an embed to post with it",
+			)
+			.max_length(256),
+		)
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::String, "embed", "Text of an embed to post with it")
+				.max_length(4000),
+		)
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::String,
+			"repeat",
+			"Post it again, like every 1d, every 12h, or every monday 09:00",
+		))
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::String,
+			"ping",
+			"Who to ping, like @someone @some-role or @here",
+		))
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::Boolean,
+			"announce",
+			"Publish it to the servers following the channel, if it's an announcement channel",
+		))
+}
+
+pub async fn run(db: &Db, limits: &Limits, ctx: &Context, command: &CommandInteraction) {
+	let content = match schedule(db, limits, ctx, command).await {
+		Ok(content) | Err(content) => content,
+	};
+
+	// the confirmation shows who will be pinged, without pinging them yet
+	let builder = CreateInteractionResponse::Message(
+		CreateInteractionResponseMessage::new()
+			.ephemeral(true)
+			.content(content)
+			.allowed_mentions(CreateAllowedMentions::new()),
+	);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
+}
+
+/// Checks and stores the scheduled message, returning what to answer.
+async fn schedule(db: &Db, limits: &Limits, ctx: &Context, command: &CommandInteraction) -> Result<String, String> {
+	let now = date_time_now();
+	let tz = user_timezone(db, command.user.id);
+
+	let mut channel_id = None;
+	let mut time = "";
+	let mut message = None;
+	let mut embed_title = None;
+	let mut embed_description = None;
+	let mut repeat = None;
+	let mut ping = "";
+	let mut announce = false;
+
+	for option in &command.data.options {
+		match option.name.as_str() {
+			"channel" => channel_id = option.value.as_channel_id(),
+			"time" => time = option.value.as_str().unwrap(),
+			"content" => message = option.value.as_str(),
+			"embed_title" => embed_title = option.value.as_str().map(str::to_string),
+			"embed" => embed_description = option.value.as_str().map(str::to_string),
+			"repeat" => repeat = option.value.as_str(),
+			"ping" => ping = option.value.as_str().unwrap_or_default(),
+			"announce" => announce = option.value.as_bool().unwrap_or_default(),
+			s => tracing::error!("Invalid option {s:?}"),
+		}
+	}
+
+	let channel_id = channel_id.unwrap();
+
+	// resolved channels come with the permissions of whoever used the command
+	let channel = command.data.resolved.channels.get(&channel_id);
+	let permissions = channel.and_then(|channel| channel.permissions).unwrap_or_default();
+	if !permissions.manage_messages() {
+		return Err(format!("You can't manage messages in <#{channel_id}>, sorry :c"));
+	}
+
+	if announce && channel.is_none_or(|channel| channel.kind != ChannelType::News) {
+		return Err(format!(
+			"<#{channel_id}> isn't an announcement channel, I can't publish anything there :c"
+		));
+	}
+
+	if message.is_none() && embed_title.is_none() && embed_description.is_none() {
+		return Err("What should I post? Give me some `content`, an `embed`, or both~".to_string());
+	}
+
+	let recurrence = match repeat {
+		None => None,
+		Some(repeat) => Some(parse_recurrence(repeat).map_err(|e| error_message(&e, repeat) + WRONG_REPEAT)?),
+	};
+
+	let timestamp = parse_time_expr(&tz.from_utc_datetime(&now.naive_utc()), time)
+		.map_err(|e| error_message(&e, time) + WRONG)?
+		.timestamp();
+
+	if timestamp <= now.timestamp() {
+		return Err("Sweetie, I don't have a time machine! :c".to_string());
+	}
+
+	let mentions = Mentions::parse(ping)
+		.map_err(|word| format!("I don't know who `{word}` is :c Ping people like @someone, @some-role or @here~"))?;
+
+	let forbidden = mentions.without(&permitted_mentions(ctx, command.user.id, channel_id, &mentions).await);
+	if !forbidden.is_empty() {
+		return Err(format!(
+			"Sorry, you're not allowed to ping {forbidden} in <#{channel_id}> :c"
+		));
+	}
+
+	let reminder = Reminder {
+		id: 0,
+		timestamp,
+		user_id: command.user.id,
+		channel_id,
+		guild_id: command.guild_id,
+		message: message.unwrap_or_default().to_string(),
+		recurrence,
+		deliver: Delivery::Channel,
+		mentions,
+		subscribers: Vec::new(),
+		post: Some(Post {
+			embed_title,
+			embed_description,
+			announce,
+		}),
+		retry: None,
+	};
+
+	limits
+		.check_new(db, std::slice::from_ref(&reminder), now.timestamp())
+		.map_err(|e| limits::error_message(&e))?;
+
+	let id = db.add_reminder(&reminder).unwrap();
+
+	let mut content = format!(
+		"Okie, will post it in <#{channel_id}> <t:{timestamp}:R> (on {})",
+		format_in_timezone(timestamp, tz)
+	);
+
+	if let Some(recurrence) = recurrence {
+		content += &format!(", and then {recurrence}");
+	}
+
+	content += &format!(" ~ (`{id}` in /myreminders)");
+	Ok(content)
+}
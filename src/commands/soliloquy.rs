@@ -0,0 +1,130 @@
+use serenity::all::{
+	CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+	CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+use crate::commands::can_manage_channel;
+use crate::commands::channelrules::CHANNEL_TYPES;
+use crate::db::Db;
+
+pub const NAME: &str = "soliloquy";
+pub const DESCRIPTION: &str = "Choose the channels where people monologue, and I delete pings and replies~";
+
+pub fn register() -> CreateCommand {
+	let channel_option = || {
+		CreateCommandOption::new(
+			CommandOptionType::Channel,
+			"channel",
+			"Which channel (this one if unspecified)",
+		)
+		.channel_types(CHANNEL_TYPES.to_vec())
+	};
+
+	CreateCommand::new(NAME)
+		.description(DESCRIPTION)
+		.contexts(vec![InteractionContext::Guild])
+		.default_member_permissions(Permissions::MANAGE_CHANNELS)
+		.add_option(
+			CreateCommandOption::new(
+				CommandOptionType::SubCommand,
+				"enable",
+				"Make a channel a soliloquy channel",
+			)
+			.add_sub_option(channel_option())
+			.add_sub_option(CreateCommandOption::new(
+				CommandOptionType::Boolean,
+				"threads",
+				"Its threads too",
+			)),
+		)
+		.add_option(
+			CreateCommandOption::new(
+				CommandOptionType::SubCommand,
+				"disable",
+				"Make a channel a normal one again",
+			)
+			.add_sub_option(channel_option()),
+		)
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::SubCommand,
+			"list",
+			"Show the soliloquy channels of this server",
+		))
+}
+
+pub async fn run(db: &Db, ctx: &Context, command: &CommandInteraction) {
+	let Some(guild_id) = command.guild_id else {
+		tracing::error!("/{NAME} used outside of a server");
+		return;
+	};
+
+	let Some(subcommand) = command.data.options.first() else {
+		tracing::error!("No subcommand given to /{NAME}");
+		return;
+	};
+
+	let content = match (subcommand.name.as_str(), &subcommand.value) {
+		(action @ ("enable" | "disable"), CommandDataOptionValue::SubCommand(options)) => {
+			let channel_id = (options.iter())
+				.find(|option| option.name == "channel")
+				.and_then(|option| option.value.as_channel_id());
+			let threads = (options.iter())
+				.find(|option| option.name == "threads")
+				.and_then(|option| option.value.as_bool())
+				.unwrap_or_default();
+
+			match channel_id {
+				Some(channel_id) if !can_manage_channel(command, channel_id) => {
+					format!("You can't manage <#{channel_id}>, sorry :c")
+				}
+				// the channel option only offers the right kinds, but this one could be a thread or a voice chat
+				None if (command.channel.as_ref()).is_none_or(|channel| !CHANNEL_TYPES.contains(&channel.kind)) => {
+					"That only works in text, announcement and forum channels~ Pick one with `channel`, \
+					its threads can be included with `threads`!"
+						.to_string()
+				}
+				channel_id => {
+					let channel_id = channel_id.unwrap_or(command.channel_id);
+					if action == "enable" {
+						let and_threads = if threads { " and its threads" } else { "" };
+						match db.enable_soliloquy(guild_id, channel_id, threads).unwrap() {
+							true => format!(
+								"Okie, <#{channel_id}>{and_threads} is a soliloquy channel now! I'll delete pings and replies there~"
+							),
+							false => format!("<#{channel_id}>{and_threads} already is a soliloquy channel~"),
+						}
+					} else {
+						match db.disable_soliloquy(guild_id, channel_id).unwrap() {
+							true => format!("Okie, people can ping and reply in <#{channel_id}> again~"),
+							false => format!("<#{channel_id}> isn't a soliloquy channel anyway~"),
+						}
+					}
+				}
+			}
+		}
+		("list", _) => {
+			let channels = db.soliloquy_channels(guild_id).unwrap();
+			if channels.is_empty() {
+				"There are no soliloquy channels in this server~ Make one with `/soliloquy enable`!".to_string()
+			} else {
+				let list: Vec<_> = (channels.iter())
+					.map(|(channel_id, threads)| match threads {
+						true => format!("- <#{channel_id}> and its threads"),
+						false => format!("- <#{channel_id}>"),
+					})
+					.collect();
+				format!("Soliloquy channels of this server:\n{}", list.join("\n"))
+			}
+		}
+		(s, _) => {
+			tracing::error!("Invalid subcommand {s:?}");
+			return;
+		}
+	};
+
+	let response_message = CreateInteractionResponseMessage::new().ephemeral(true).content(content);
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
+}
@@ -0,0 +1,180 @@
+use serenity::all::{
+	CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateAllowedMentions,
+	CreateCommand, CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage, GuildId,
+	InteractionContext, Permissions,
+};
+
+use crate::db::Db;
+use crate::reminders::{date_time_now, excerpt};
+use crate::strikes::StrikePolicy;
+
+pub const NAME: &str = "strikes";
+pub const DESCRIPTION: &str = "See and clear the strikes people got for breaking channel rules~";
+
+/// Longest strikes can count, about ten years.
+const MAX_DECAY_DAYS: i64 = 3650;
+
+/// How many strikes `/strikes view` lists at most, the latest ones, so it fits in a message.
+const VIEW_MAX_STRIKES: usize = 15;
+
+pub fn register() -> CreateCommand {
+	let user_option = || CreateCommandOption::new(CommandOptionType::User, "user", "Whose strikes").required(true);
+	let step_option = |name: &str, description: &str| {
+		CreateCommandOption::new(CommandOptionType::Integer, name, description).min_int_value(0)
+	};
+
+	CreateCommand::new(NAME)
+		.description(DESCRIPTION)
+		.contexts(vec![InteractionContext::Guild])
+		.default_member_permissions(Permissions::MODERATE_MEMBERS)
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::SubCommand, "view", "Show someone's strikes")
+				.add_sub_option(user_option()),
+		)
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::SubCommand, "clear", "Forget someone's strikes")
+				.add_sub_option(user_option()),
+		)
+		.add_option(
+			CreateCommandOption::new(
+				CommandOptionType::SubCommand,
+				"settings",
+				"Show or change how strikes add up",
+			)
+			.add_sub_option(
+				CreateCommandOption::new(
+					CommandOptionType::Integer,
+					"decay_days",
+					"How many days a strike counts",
+				)
+				.min_int_value(1)
+				.max_int_value(MAX_DECAY_DAYS as u64),
+			)
+			.add_sub_option(step_option(
+				"timeout_after",
+				"From how many strikes people get timed out (0 to never)",
+			))
+			.add_sub_option(
+				CreateCommandOption::new(CommandOptionType::Integer, "timeout_minutes", "How long timeouts last")
+					.min_int_value(1)
+					.max_int_value(28 * 24 * 60),
+			)
+			.add_sub_option(step_option(
+				"notify_after",
+				"At how many strikes I tell the moderators in the mod-log (0 to never)",
+			)),
+		)
+}
+
+pub async fn run(db: &Db, ctx: &Context, command: &CommandInteraction) {
+	let Some(guild_id) = command.guild_id else {
+		tracing::error!("/{NAME} used outside of a server");
+		return;
+	};
+
+	let Some(subcommand) = command.data.options.first() else {
+		tracing::error!("No subcommand given to /{NAME}");
+		return;
+	};
+
+	let CommandDataOptionValue::SubCommand(options) = &subcommand.value else {
+		tracing::error!("Invalid subcommand {:?}", subcommand.name);
+		return;
+	};
+
+	let user_id = (options.iter())
+		.find(|option| option.name == "user")
+		.and_then(|option| option.value.as_user_id());
+
+	let content = match (subcommand.name.as_str(), user_id) {
+		("view", Some(user_id)) => {
+			let policy = db.strike_policy(guild_id).unwrap();
+			let strikes = db
+				.strikes(guild_id, user_id, policy.since(date_time_now().timestamp()))
+				.unwrap();
+
+			if strikes.is_empty() {
+				format!("<@{user_id}> has no strikes~")
+			} else {
+				let mut lines: Vec<_> = (strikes.iter().rev().take(VIEW_MAX_STRIKES).rev())
+					.map(|(timestamp, rule)| format!("- <t:{timestamp}:f>: {}", excerpt(rule, 80)))
+					.collect();
+				if strikes.len() > VIEW_MAX_STRIKES {
+					lines.insert(0, format!("...and {} older ones", strikes.len() - VIEW_MAX_STRIKES));
+				}
+
+				format!(
+					"<@{user_id}> has {} strikes from the last {} days:\n{}",
+					strikes.len(),
+					policy.decay_days,
+					lines.join("\n")
+				)
+			}
+		}
+		("clear", Some(user_id)) => match db.clear_strikes(guild_id, user_id).unwrap() {
+			0 => format!("<@{user_id}> had no strikes anyway~"),
+			_ => format!("Okie, <@{user_id}> has a clean record now~"),
+		},
+		("settings", _) => settings(db, guild_id, options),
+		(s, _) => {
+			tracing::error!("Invalid subcommand {s:?}");
+			return;
+		}
+	};
+
+	let response_message = CreateInteractionResponseMessage::new()
+		.ephemeral(true)
+		.content(content)
+		.allowed_mentions(CreateAllowedMentions::new());
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
+}
+
+fn settings(db: &Db, guild_id: GuildId, options: &[CommandDataOption]) -> String {
+	let mut policy = db.strike_policy(guild_id).unwrap();
+
+	for option in options {
+		let Some(value) = option.value.as_i64() else {
+			continue;
+		};
+
+		match option.name.as_str() {
+			"decay_days" => policy.decay_days = value.clamp(1, MAX_DECAY_DAYS),
+			"timeout_after" => policy.timeout_after = Some(value as usize).filter(|&n| n > 0),
+			"timeout_minutes" => policy.timeout_minutes = value,
+			"notify_after" => policy.notify_after = Some(value as usize).filter(|&n| n > 0),
+			s => tracing::error!("Invalid option {s:?}"),
+		}
+	}
+
+	let intro = match options.is_empty() {
+		true => "Here's how strikes work in this server~",
+		false => {
+			db.set_strike_policy(guild_id, &policy).unwrap();
+			"Okie, here's how strikes work in this server now~"
+		}
+	};
+
+	format!("{intro}\n{}", describe(&policy))
+}
+
+fn describe(policy: &StrikePolicy) -> String {
+	let mut lines = vec![format!("- Strikes count for {} days", policy.decay_days)];
+
+	lines.push(match policy.timeout_after {
+		Some(after) => format!(
+			"- From {after} strikes, people get timed out for {} minutes",
+			policy.timeout_minutes
+		),
+		None => "- Nobody gets timed out".to_string(),
+	});
+
+	lines.push(match policy.notify_after {
+		Some(after) => format!("- At {after} strikes, I tell the moderators in the mod-log"),
+		None => "- I don't tell the moderators about repeat offenders".to_string(),
+	});
+
+	lines.join("\n")
+}
@@ -0,0 +1,82 @@
+use chrono_tz::Tz;
+use serenity::all::{
+	CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+	CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use crate::db::Db;
+use crate::reminders::date_time_now;
+use crate::timezones::{format_in_timezone, user_timezone};
+
+pub const NAME: &str = "timezone";
+pub const DESCRIPTION: &str = "Tell me your time zone so I read and show dates the way you do~";
+
+pub fn register() -> CreateCommand {
+	CreateCommand::new(NAME)
+		.description(DESCRIPTION)
+		.add_option(
+			CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Set your time zone").add_sub_option(
+				CreateCommandOption::new(
+					CommandOptionType::String,
+					"name",
+					"IANA time zone name, like Europe/Paris or America/New_York",
+				)
+				.required(true),
+			),
+		)
+		.add_option(CreateCommandOption::new(
+			CommandOptionType::SubCommand,
+			"show",
+			"Show the time zone I'm using for you",
+		))
+}
+
+pub async fn run(db: &Db, ctx: &Context, command: &CommandInteraction) {
+	let Some(subcommand) = command.data.options.first() else {
+		tracing::error!("No subcommand given to /{NAME}");
+		return;
+	};
+
+	let content = match (subcommand.name.as_str(), &subcommand.value) {
+		("set", CommandDataOptionValue::SubCommand(options)) => {
+			let name = (options.iter())
+				.find(|option| option.name == "name")
+				.and_then(|option| option.value.as_str())
+				.unwrap_or_default();
+
+			match name.trim().parse::<Tz>() {
+				Ok(tz) => {
+					db.set_timezone(command.user.id, tz).unwrap();
+
+					format!(
+						"Okie, your time zone is now `{}`! It's currently {} for you~",
+						tz.name(),
+						format_in_timezone(date_time_now().timestamp(), tz)
+					)
+				}
+				Err(_) => format!(
+					"I don't know any time zone called `{}` :c\nTry an IANA name like `Europe/Paris`, `America/New_York` or `Asia/Tokyo`.",
+					name.replace('`', "ˋ")
+				),
+			}
+		}
+		("show", _) => {
+			let tz = user_timezone(db, command.user.id);
+			format!(
+				"Your time zone is `{}`. It's currently {} for you~",
+				tz.name(),
+				format_in_timezone(date_time_now().timestamp(), tz)
+			)
+		}
+		(s, _) => {
+			tracing::error!("Invalid subcommand {s:?}");
+			return;
+		}
+	};
+
+	let response_message = CreateInteractionResponseMessage::new().ephemeral(true).content(content);
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = command.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to slash command: {e}");
+	}
+}
@@ -0,0 +1,929 @@
+use std::sync::Mutex;
+
+use chrono::{NaiveTime, Timelike, Weekday};
+use chrono_tz::Tz;
+use rusqlite::{ffi, params, Connection, OptionalExtension, Row};
+use serenity::all::{ChannelId, GuildId, UserId};
+use tokio::sync::Notify;
+
+use crate::delivery::Delivery;
+use crate::mentions::Mentions;
+use crate::reminders::{AfterSending, Post, Recurrence, Reminder, Retry};
+use crate::rules::{ChannelRule, Rule};
+use crate::strikes::StrikePolicy;
+
+pub const DB_FILE_NAME: &str = "ai-chan.db";
+
+/// Schema migrations, in order.
+/// The index of the last one applied is kept in SQLite's `user_version`, so only append to this list.
+const MIGRATIONS: &[&str] = &[
+	// 1: reminders and user settings
+	"
+	CREATE TABLE reminders (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		timestamp INTEGER NOT NULL,
+		user_id INTEGER NOT NULL,
+		channel_id INTEGER NOT NULL,
+		message TEXT NOT NULL,
+		repeat_every INTEGER,
+		repeat_weekday INTEGER,
+		repeat_time INTEGER,
+		deliver INTEGER NOT NULL DEFAULT 0
+	);
+
+	CREATE INDEX reminders_by_timestamp ON reminders (timestamp);
+	CREATE INDEX reminders_by_user ON reminders (user_id, timestamp);
+
+	CREATE TABLE user_settings (
+		user_id INTEGER PRIMARY KEY,
+		timezone TEXT,
+		delivery INTEGER
+	);
+	",
+	// 2: retry queue
+	"
+	ALTER TABLE reminders ADD COLUMN retry_attempts INTEGER NOT NULL DEFAULT 0;
+	ALTER TABLE reminders ADD COLUMN retry_due INTEGER;
+	",
+	// 3: reminders pinging other users, roles or @here
+	"
+	ALTER TABLE reminders ADD COLUMN mentions TEXT NOT NULL DEFAULT '';
+	",
+	// 4: users who joined reminders made by others
+	"
+	CREATE TABLE reminder_subscribers (
+		reminder_id INTEGER NOT NULL,
+		user_id INTEGER NOT NULL,
+		PRIMARY KEY (reminder_id, user_id)
+	);
+
+	CREATE INDEX reminder_subscribers_by_user ON reminder_subscribers (user_id);
+	",
+	// 5: messages scheduled with /schedule
+	"
+	ALTER TABLE reminders ADD COLUMN post INTEGER NOT NULL DEFAULT 0;
+	ALTER TABLE reminders ADD COLUMN post_embed_title TEXT;
+	ALTER TABLE reminders ADD COLUMN post_embed_description TEXT;
+	ALTER TABLE reminders ADD COLUMN post_announce INTEGER NOT NULL DEFAULT 0;
+	",
+	// 6: server of the reminders, to limit how many each server has
+	"
+	ALTER TABLE reminders ADD COLUMN guild_id INTEGER;
+
+	CREATE INDEX reminders_by_guild ON reminders (guild_id);
+	",
+	// 7: soliloquy channels, starting with the one that used to be hard-coded, whose server wasn't known.
+	// Enabling it again from its server fills that in.
+	"
+	CREATE TABLE soliloquy_channels (
+		channel_id INTEGER PRIMARY KEY,
+		guild_id INTEGER
+	);
+
+	CREATE INDEX soliloquy_channels_by_guild ON soliloquy_channels (guild_id);
+
+	INSERT INTO soliloquy_channels (channel_id) VALUES (1137703122408575077);
+	",
+	// 8: rules of channels, checked on every message
+	"
+	CREATE TABLE channel_rules (
+		id INTEGER PRIMARY KEY,
+		guild_id INTEGER NOT NULL,
+		channel_id INTEGER NOT NULL,
+		kind TEXT NOT NULL,
+		value TEXT,
+		message TEXT
+	);
+
+	CREATE INDEX channel_rules_by_channel ON channel_rules (channel_id);
+	CREATE INDEX channel_rules_by_guild ON channel_rules (guild_id);
+	",
+	// 9: rules also enforced in the threads and forum posts of their channel
+	"
+	ALTER TABLE soliloquy_channels ADD COLUMN threads INTEGER NOT NULL DEFAULT 0;
+	ALTER TABLE channel_rules ADD COLUMN threads INTEGER NOT NULL DEFAULT 0;
+	",
+	// 10: server settings, starting with where to log what moderators and I do
+	"
+	CREATE TABLE guild_settings (
+		guild_id INTEGER PRIMARY KEY,
+		mod_log_channel INTEGER
+	);
+	",
+	// 11: strikes of users breaking channel rules, and how they escalate in each server
+	"
+	CREATE TABLE strikes (
+		id INTEGER PRIMARY KEY,
+		guild_id INTEGER NOT NULL,
+		user_id INTEGER NOT NULL,
+		timestamp INTEGER NOT NULL,
+		rule TEXT NOT NULL
+	);
+
+	CREATE INDEX strikes_by_user ON strikes (guild_id, user_id, timestamp);
+
+	ALTER TABLE guild_settings ADD COLUMN strike_decay_days INTEGER;
+	ALTER TABLE guild_settings ADD COLUMN strike_timeout_after INTEGER;
+	ALTER TABLE guild_settings ADD COLUMN strike_timeout_minutes INTEGER;
+	ALTER TABLE guild_settings ADD COLUMN strike_notify_after INTEGER;
+	",
+	// 12: delivered reminders that can still be snoozed, as their buttons can't hold the whole text
+	"
+	CREATE TABLE snoozable_reminders (
+		id INTEGER PRIMARY KEY,
+		user_id INTEGER NOT NULL,
+		message TEXT NOT NULL,
+		timestamp INTEGER NOT NULL
+	);
+	",
+];
+
+const REMINDER_COLUMNS: &str = "id, timestamp, user_id, channel_id, message, repeat_every, repeat_weekday, \
+	repeat_time, deliver, retry_attempts, retry_due, mentions, post, post_embed_title, post_embed_description, \
+	post_announce, guild_id";
+
+/// The subscribers of the reminder, as space-separated user IDs, to select after [`REMINDER_COLUMNS`].
+const SUBSCRIBERS_COLUMN: &str =
+	"(SELECT group_concat(user_id, ' ') FROM reminder_subscribers WHERE reminder_id = reminders.id)";
+
+/// AI-chan's persistent state, in a SQLite database.
+pub struct Db {
+	conn: Mutex<Connection>,
+	/// Wakes the reminder scheduler up when reminders are added, edited or deleted.
+	reminders_changed: Notify,
+}
+
+impl Db {
+	/// Opens the database, creating it if needed and bringing its schema up to date.
+	pub fn open(path: &str) -> rusqlite::Result<Self> {
+		let mut conn = Connection::open(path)?;
+		conn.pragma_update(None, "journal_mode", "WAL")?;
+		migrate(&mut conn)?;
+
+		Ok(Self {
+			conn: Mutex::new(conn),
+			reminders_changed: Notify::new(),
+		})
+	}
+
+	/// Resolves the next time reminders are added, edited or deleted, or right away if they were since the last call.
+	pub async fn reminders_changed(&self) {
+		self.reminders_changed.notified().await;
+	}
+
+	/// Adds a new reminder, ignoring its ID and subscribers. Returns the ID it was given.
+	pub fn add_reminder(&self, reminder: &Reminder) -> rusqlite::Result<i64> {
+		let conn = self.conn.lock().unwrap();
+		let (repeat_every, repeat_weekday, repeat_time) = recurrence_columns(reminder.recurrence);
+		let (post, embed_title, embed_description, announce) = post_columns(&reminder.post);
+
+		conn.execute(
+			"INSERT INTO reminders
+				(timestamp, user_id, channel_id, message, repeat_every, repeat_weekday, repeat_time, deliver, mentions,
+				post, post_embed_title, post_embed_description, post_announce, guild_id)
+			VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+			params![
+				reminder.timestamp,
+				reminder.user_id.get() as i64,
+				reminder.channel_id.get() as i64,
+				reminder.message,
+				repeat_every,
+				repeat_weekday,
+				repeat_time,
+				reminder.deliver.to_byte(),
+				reminder.mentions.to_string(),
+				post,
+				embed_title,
+				embed_description,
+				announce,
+				reminder.guild_id.map(|guild_id| guild_id.get() as i64),
+			],
+		)?;
+
+		self.reminders_changed.notify_one();
+		Ok(conn.last_insert_rowid())
+	}
+
+	/// Saves the changes made to an existing reminder.
+	pub fn update_reminder(&self, reminder: &Reminder) -> rusqlite::Result<()> {
+		let conn = self.conn.lock().unwrap();
+		let (repeat_every, repeat_weekday, repeat_time) = recurrence_columns(reminder.recurrence);
+		let (post, embed_title, embed_description, announce) = post_columns(&reminder.post);
+
+		conn.execute(
+			"UPDATE reminders SET
+				timestamp = ?2, channel_id = ?3, message = ?4,
+				repeat_every = ?5, repeat_weekday = ?6, repeat_time = ?7, deliver = ?8,
+				retry_attempts = ?9, retry_due = ?10, mentions = ?11,
+				post = ?12, post_embed_title = ?13, post_embed_description = ?14, post_announce = ?15
+			WHERE id = ?1",
+			params![
+				reminder.id,
+				reminder.timestamp,
+				reminder.channel_id.get() as i64,
+				reminder.message,
+				repeat_every,
+				repeat_weekday,
+				repeat_time,
+				reminder.deliver.to_byte(),
+				reminder.retry.map_or(0, |retry| retry.attempts),
+				reminder.retry.map(|retry| retry.due),
+				reminder.mentions.to_string(),
+				post,
+				embed_title,
+				embed_description,
+				announce,
+			],
+		)?;
+
+		self.reminders_changed.notify_one();
+		Ok(())
+	}
+
+	/// Saves what happened to the reminders the scheduler tried to send, all at once.
+	///
+	/// Each comes with the timestamp it was due at when picked up. Reminders edited since then are left alone,
+	/// as what was decided for them no longer applies.
+	pub fn finish_reminders(&self, sent: &[(i64, i64, AfterSending)]) -> rusqlite::Result<()> {
+		let mut conn = self.conn.lock().unwrap();
+		let tx = conn.transaction()?;
+
+		for &(id, due, after_sending) in sent {
+			match after_sending {
+				AfterSending::Delete => {
+					let deleted = tx.execute("DELETE FROM reminders WHERE id = ?1 AND timestamp = ?2", [id, due])?;
+					if deleted > 0 {
+						tx.execute("DELETE FROM reminder_subscribers WHERE reminder_id = ?1", [id])?;
+					}
+				}
+				AfterSending::Reschedule(timestamp) => {
+					tx.execute(
+						"UPDATE reminders SET timestamp = ?3, retry_attempts = 0, retry_due = NULL
+						WHERE id = ?1 AND timestamp = ?2",
+						[id, due, timestamp],
+					)?;
+				}
+				AfterSending::Retry(timestamp, retry) => {
+					tx.execute(
+						"UPDATE reminders SET timestamp = ?3, retry_attempts = ?4, retry_due = ?5
+						WHERE id = ?1 AND timestamp = ?2",
+						params![id, due, timestamp, retry.attempts, retry.due],
+					)?;
+				}
+			}
+		}
+
+		tx.commit()
+	}
+
+	/// Deletes some reminders of a user, or takes them out of the ones they joined.
+	/// Returns how many were actually deleted or left.
+	pub fn delete_user_reminders(&self, user_id: UserId, ids: &[i64]) -> rusqlite::Result<usize> {
+		let mut conn = self.conn.lock().unwrap();
+		let tx = conn.transaction()?;
+
+		let mut deleted = 0;
+		for &id in ids {
+			let owned = tx.execute(
+				"DELETE FROM reminders WHERE id = ?1 AND user_id = ?2",
+				params![id, user_id.get() as i64],
+			)?;
+
+			if owned > 0 {
+				tx.execute("DELETE FROM reminder_subscribers WHERE reminder_id = ?1", [id])?;
+				deleted += owned;
+			} else {
+				deleted += tx.execute(
+					"DELETE FROM reminder_subscribers WHERE reminder_id = ?1 AND user_id = ?2",
+					params![id, user_id.get() as i64],
+				)?;
+			}
+		}
+
+		tx.commit()?;
+		self.reminders_changed.notify_one();
+		Ok(deleted)
+	}
+
+	/// All the reminders of a user, including the ones they joined, soonest first.
+	pub fn user_reminders(&self, user_id: UserId) -> rusqlite::Result<Vec<Reminder>> {
+		let conn = self.conn.lock().unwrap();
+		let mut stmt = conn.prepare_cached(&format!(
+			"SELECT {REMINDER_COLUMNS}, {SUBSCRIBERS_COLUMN} FROM reminders
+			WHERE user_id = ?1 OR id IN (SELECT reminder_id FROM reminder_subscribers WHERE user_id = ?1)
+			ORDER BY timestamp, id"
+		))?;
+
+		let reminders = stmt.query_map([user_id.get() as i64], reminder_from_row)?;
+		reminders.collect()
+	}
+
+	/// A reminder of a user, or one they joined.
+	pub fn user_reminder(&self, user_id: UserId, id: i64) -> rusqlite::Result<Option<Reminder>> {
+		let conn = self.conn.lock().unwrap();
+		let mut stmt = conn.prepare_cached(&format!(
+			"SELECT {REMINDER_COLUMNS}, {SUBSCRIBERS_COLUMN} FROM reminders
+			WHERE id = ?1 AND (user_id = ?2 OR id IN (SELECT reminder_id FROM reminder_subscribers WHERE user_id = ?2))"
+		))?;
+
+		stmt.query_row(params![id, user_id.get() as i64], reminder_from_row)
+			.optional()
+	}
+
+	/// Reminders due at or before `now`, oldest first.
+	pub fn due_reminders(&self, now: i64) -> rusqlite::Result<Vec<Reminder>> {
+		let conn = self.conn.lock().unwrap();
+		let mut stmt = conn.prepare_cached(&format!(
+			"SELECT {REMINDER_COLUMNS}, {SUBSCRIBERS_COLUMN} FROM reminders WHERE timestamp <= ?1 ORDER BY timestamp, id"
+		))?;
+
+		let reminders = stmt.query_map([now], reminder_from_row)?;
+		reminders.collect()
+	}
+
+	/// How many reminders the user made that are still waiting to go off.
+	pub fn count_user_reminders(&self, user_id: UserId) -> rusqlite::Result<usize> {
+		let conn = self.conn.lock().unwrap();
+		conn.query_row(
+			"SELECT COUNT(*) FROM reminders WHERE user_id = ?1",
+			[user_id.get() as i64],
+			|row| row.get(0),
+		)
+	}
+
+	/// How many reminders were made in the server and are still waiting to go off.
+	pub fn count_guild_reminders(&self, guild_id: GuildId) -> rusqlite::Result<usize> {
+		let conn = self.conn.lock().unwrap();
+		conn.query_row(
+			"SELECT COUNT(*) FROM reminders WHERE guild_id = ?1",
+			[guild_id.get() as i64],
+			|row| row.get(0),
+		)
+	}
+
+	/// Adds the user to the people the reminder pings, as long as it has less than `max` of them.
+	/// Returns false if it's full, if there's no such reminder, or if it's theirs in the first place.
+	pub fn subscribe(&self, reminder_id: i64, user_id: UserId, max: usize) -> rusqlite::Result<bool> {
+		let conn = self.conn.lock().unwrap();
+		let added = conn.execute(
+			"INSERT OR IGNORE INTO reminder_subscribers (reminder_id, user_id)
+			SELECT id, ?2 FROM reminders WHERE id = ?1 AND user_id != ?2
+				AND (SELECT COUNT(*) FROM reminder_subscribers WHERE reminder_id = ?1) < ?3",
+			params![reminder_id, user_id.get() as i64, max],
+		)?;
+
+		Ok(added > 0)
+	}
+
+	pub fn count_subscribers(&self, reminder_id: i64) -> rusqlite::Result<usize> {
+		let conn = self.conn.lock().unwrap();
+		conn.query_row(
+			"SELECT COUNT(*) FROM reminder_subscribers WHERE reminder_id = ?1",
+			[reminder_id],
+			|row| row.get(0),
+		)
+	}
+
+	/// Removes the user from the people the reminder pings. Returns false if they weren't in it.
+	pub fn unsubscribe(&self, reminder_id: i64, user_id: UserId) -> rusqlite::Result<bool> {
+		let conn = self.conn.lock().unwrap();
+		let removed = conn.execute(
+			"DELETE FROM reminder_subscribers WHERE reminder_id = ?1 AND user_id = ?2",
+			params![reminder_id, user_id.get() as i64],
+		)?;
+
+		Ok(removed > 0)
+	}
+
+	/// Timestamp of the reminder due the soonest.
+	pub fn next_timestamp(&self) -> rusqlite::Result<Option<i64>> {
+		let conn = self.conn.lock().unwrap();
+		conn.query_row("SELECT MIN(timestamp) FROM reminders", [], |row| row.get(0))
+	}
+
+	pub fn timezone(&self, user_id: UserId) -> rusqlite::Result<Option<Tz>> {
+		let conn = self.conn.lock().unwrap();
+		let name = conn
+			.query_row(
+				"SELECT timezone FROM user_settings WHERE user_id = ?1",
+				[user_id.get() as i64],
+				|row| row.get::<_, Option<String>>(0),
+			)
+			.optional()?
+			.flatten();
+
+		// time zones can disappear from the database, in which case the user just falls back to UTC
+		Ok(name.and_then(|name| match name.parse::<Tz>() {
+			Ok(tz) => Some(tz),
+			Err(_) => {
+				tracing::warn!("Ignoring unknown time zone {name:?} of user {user_id}");
+				None
+			}
+		}))
+	}
+
+	pub fn set_timezone(&self, user_id: UserId, tz: Tz) -> rusqlite::Result<()> {
+		let conn = self.conn.lock().unwrap();
+		conn.execute(
+			"INSERT INTO user_settings (user_id, timezone) VALUES (?1, ?2)
+			ON CONFLICT (user_id) DO UPDATE SET timezone = excluded.timezone",
+			params![user_id.get() as i64, tz.name()],
+		)?;
+		Ok(())
+	}
+
+	pub fn delivery(&self, user_id: UserId) -> rusqlite::Result<Option<Delivery>> {
+		let conn = self.conn.lock().unwrap();
+		let byte = conn
+			.query_row(
+				"SELECT delivery FROM user_settings WHERE user_id = ?1",
+				[user_id.get() as i64],
+				|row| row.get::<_, Option<u8>>(0),
+			)
+			.optional()?
+			.flatten();
+
+		Ok(byte.and_then(Delivery::from_byte))
+	}
+
+	pub fn set_delivery(&self, user_id: UserId, delivery: Delivery) -> rusqlite::Result<()> {
+		let conn = self.conn.lock().unwrap();
+		conn.execute(
+			"INSERT INTO user_settings (user_id, delivery) VALUES (?1, ?2)
+			ON CONFLICT (user_id) DO UPDATE SET delivery = excluded.delivery",
+			params![user_id.get() as i64, delivery.to_byte()],
+		)?;
+		Ok(())
+	}
+
+	/// Whether the channel is a soliloquy channel, and if so whether its threads are too.
+	pub fn soliloquy(&self, channel_id: ChannelId) -> rusqlite::Result<Option<bool>> {
+		let conn = self.conn.lock().unwrap();
+		conn.query_row(
+			"SELECT threads FROM soliloquy_channels WHERE channel_id = ?1",
+			[channel_id.get() as i64],
+			|row| row.get(0),
+		)
+		.optional()
+	}
+
+	/// Soliloquy channels of the server, and whether their threads are too.
+	pub fn soliloquy_channels(&self, guild_id: GuildId) -> rusqlite::Result<Vec<(ChannelId, bool)>> {
+		let conn = self.conn.lock().unwrap();
+		let mut stmt =
+			conn.prepare("SELECT channel_id, threads FROM soliloquy_channels WHERE guild_id = ?1 ORDER BY channel_id")?;
+
+		let channels = stmt.query_map([guild_id.get() as i64], |row| {
+			Ok((ChannelId::new(row.get::<_, i64>(0)? as u64), row.get(1)?))
+		})?;
+		channels.collect()
+	}
+
+	/// Makes the channel a soliloquy channel, and its threads too if asked. Returns false if it already was just that.
+	pub fn enable_soliloquy(&self, guild_id: GuildId, channel_id: ChannelId, threads: bool) -> rusqlite::Result<bool> {
+		let conn = self.conn.lock().unwrap();
+		let changed = conn.execute(
+			"INSERT INTO soliloquy_channels (channel_id, guild_id, threads) VALUES (?1, ?2, ?3)
+			ON CONFLICT (channel_id) DO UPDATE SET guild_id = excluded.guild_id, threads = excluded.threads
+			WHERE guild_id IS NULL OR threads != excluded.threads",
+			params![channel_id.get() as i64, guild_id.get() as i64, threads],
+		)?;
+
+		Ok(changed > 0)
+	}
+
+	/// Whether the server has rules enforced in threads, so messages in threads need to be checked against
+	/// the rules of their parent channel.
+	pub fn has_thread_rules(&self, guild_id: GuildId) -> rusqlite::Result<bool> {
+		let conn = self.conn.lock().unwrap();
+		conn.query_row(
+			"SELECT EXISTS (SELECT 1 FROM soliloquy_channels WHERE guild_id = ?1 AND threads)
+			OR EXISTS (SELECT 1 FROM channel_rules WHERE guild_id = ?1 AND threads)",
+			[guild_id.get() as i64],
+			|row| row.get(0),
+		)
+	}
+
+	/// Makes the channel a normal channel again. Returns false if it wasn't a soliloquy channel of that server.
+	pub fn disable_soliloquy(&self, guild_id: GuildId, channel_id: ChannelId) -> rusqlite::Result<bool> {
+		let conn = self.conn.lock().unwrap();
+		let removed = conn.execute(
+			"DELETE FROM soliloquy_channels WHERE channel_id = ?1 AND (guild_id = ?2 OR guild_id IS NULL)",
+			params![channel_id.get() as i64, guild_id.get() as i64],
+		)?;
+
+		Ok(removed > 0)
+	}
+
+	pub fn channel_rules(&self, channel_id: ChannelId) -> rusqlite::Result<Vec<ChannelRule>> {
+		self.query_channel_rules("channel_id = ?1", channel_id.get())
+	}
+
+	pub fn guild_rules(&self, guild_id: GuildId) -> rusqlite::Result<Vec<ChannelRule>> {
+		self.query_channel_rules("guild_id = ?1", guild_id.get())
+	}
+
+	fn query_channel_rules(&self, condition: &str, id: u64) -> rusqlite::Result<Vec<ChannelRule>> {
+		let conn = self.conn.lock().unwrap();
+		let mut stmt = conn.prepare(&format!(
+			"SELECT id, channel_id, kind, value, message, threads FROM channel_rules WHERE {condition} ORDER BY id"
+		))?;
+
+		let rows = stmt.query_map([id as i64], |row| {
+			Ok((
+				row.get::<_, i64>(0)?,
+				ChannelId::new(row.get::<_, i64>(1)? as u64),
+				row.get::<_, String>(2)?,
+				row.get::<_, Option<String>>(3)?,
+				row.get::<_, Option<String>>(4)?,
+				row.get::<_, bool>(5)?,
+			))
+		})?;
+
+		let mut rules = Vec::new();
+		for row in rows {
+			let (id, channel_id, kind, value, message, threads) = row?;
+			match Rule::parse(&kind, value.as_deref()) {
+				Ok(rule) => rules.push(ChannelRule {
+					id,
+					channel_id,
+					rule,
+					message,
+					threads,
+				}),
+				// rules are checked before they're stored, so this only happens if what a rule accepts changes
+				Err(e) => tracing::warn!("Ignoring invalid rule {id} of channel {channel_id}: {e}"),
+			}
+		}
+
+		Ok(rules)
+	}
+
+	pub fn add_channel_rule(&self, guild_id: GuildId, rule: &ChannelRule) -> rusqlite::Result<i64> {
+		let conn = self.conn.lock().unwrap();
+		conn.execute(
+			"INSERT INTO channel_rules (guild_id, channel_id, kind, value, message, threads)
+			VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+			params![
+				guild_id.get() as i64,
+				rule.channel_id.get() as i64,
+				rule.rule.kind(),
+				rule.rule.value(),
+				rule.message,
+				rule.threads,
+			],
+		)?;
+
+		Ok(conn.last_insert_rowid())
+	}
+
+	/// Removes a rule of the server. Returns false if it has no such rule.
+	pub fn delete_channel_rule(&self, guild_id: GuildId, id: i64) -> rusqlite::Result<bool> {
+		let conn = self.conn.lock().unwrap();
+		let removed = conn.execute(
+			"DELETE FROM channel_rules WHERE id = ?1 AND guild_id = ?2",
+			params![id, guild_id.get() as i64],
+		)?;
+
+		Ok(removed > 0)
+	}
+
+	pub fn mod_log_channel(&self, guild_id: GuildId) -> rusqlite::Result<Option<ChannelId>> {
+		let conn = self.conn.lock().unwrap();
+		let channel_id = conn
+			.query_row(
+				"SELECT mod_log_channel FROM guild_settings WHERE guild_id = ?1",
+				[guild_id.get() as i64],
+				|row| row.get::<_, Option<i64>>(0),
+			)
+			.optional()?
+			.flatten();
+
+		Ok(channel_id.map(|id| ChannelId::new(id as u64)))
+	}
+
+	pub fn set_mod_log_channel(&self, guild_id: GuildId, channel_id: Option<ChannelId>) -> rusqlite::Result<()> {
+		let conn = self.conn.lock().unwrap();
+		conn.execute(
+			"INSERT INTO guild_settings (guild_id, mod_log_channel) VALUES (?1, ?2)
+			ON CONFLICT (guild_id) DO UPDATE SET mod_log_channel = excluded.mod_log_channel",
+			params![guild_id.get() as i64, channel_id.map(|id| id.get() as i64)],
+		)?;
+		Ok(())
+	}
+
+	/// How strikes work in the server, the defaults filling in what it didn't set.
+	pub fn strike_policy(&self, guild_id: GuildId) -> rusqlite::Result<StrikePolicy> {
+		let conn = self.conn.lock().unwrap();
+		let columns = conn
+			.query_row(
+				"SELECT strike_decay_days, strike_timeout_after, strike_timeout_minutes, strike_notify_after
+				FROM guild_settings WHERE guild_id = ?1",
+				[guild_id.get() as i64],
+				|row| {
+					Ok((
+						row.get::<_, Option<i64>>(0)?,
+						row.get::<_, Option<usize>>(1)?,
+						row.get::<_, Option<i64>>(2)?,
+						row.get::<_, Option<usize>>(3)?,
+					))
+				},
+			)
+			.optional()?;
+
+		let default = StrikePolicy::default();
+		let Some((decay_days, timeout_after, timeout_minutes, notify_after)) = columns else {
+			return Ok(default);
+		};
+
+		// steps are turned off with 0
+		let step = |column: Option<usize>, default| column.map_or(default, |n| Some(n).filter(|&n| n > 0));
+		Ok(StrikePolicy {
+			decay_days: decay_days.unwrap_or(default.decay_days),
+			timeout_after: step(timeout_after, default.timeout_after),
+			timeout_minutes: timeout_minutes.unwrap_or(default.timeout_minutes),
+			notify_after: step(notify_after, default.notify_after),
+		})
+	}
+
+	pub fn set_strike_policy(&self, guild_id: GuildId, policy: &StrikePolicy) -> rusqlite::Result<()> {
+		let conn = self.conn.lock().unwrap();
+		conn.execute(
+			"INSERT INTO guild_settings
+			(guild_id, strike_decay_days, strike_timeout_after, strike_timeout_minutes, strike_notify_after)
+			VALUES (?1, ?2, ?3, ?4, ?5)
+			ON CONFLICT (guild_id) DO UPDATE SET
+			strike_decay_days = excluded.strike_decay_days,
+			strike_timeout_after = excluded.strike_timeout_after,
+			strike_timeout_minutes = excluded.strike_timeout_minutes,
+			strike_notify_after = excluded.strike_notify_after",
+			params![
+				guild_id.get() as i64,
+				policy.decay_days,
+				policy.timeout_after.unwrap_or(0),
+				policy.timeout_minutes,
+				policy.notify_after.unwrap_or(0),
+			],
+		)?;
+		Ok(())
+	}
+
+	/// Gives the user a strike, forgetting those that happened before `since`.
+	/// Returns how many strikes they have now.
+	pub fn add_strike(
+		&self,
+		guild_id: GuildId,
+		user_id: UserId,
+		timestamp: i64,
+		rule: &str,
+		since: i64,
+	) -> rusqlite::Result<usize> {
+		let mut conn = self.conn.lock().unwrap();
+		let tx = conn.transaction()?;
+		let (guild, user) = (guild_id.get() as i64, user_id.get() as i64);
+
+		tx.execute(
+			"DELETE FROM strikes WHERE guild_id = ?1 AND user_id = ?2 AND timestamp < ?3",
+			params![guild, user, since],
+		)?;
+		tx.execute(
+			"INSERT INTO strikes (guild_id, user_id, timestamp, rule) VALUES (?1, ?2, ?3, ?4)",
+			params![guild, user, timestamp, rule],
+		)?;
+		let count = tx.query_row(
+			"SELECT COUNT(*) FROM strikes WHERE guild_id = ?1 AND user_id = ?2",
+			params![guild, user],
+			|row| row.get(0),
+		)?;
+
+		tx.commit()?;
+		Ok(count)
+	}
+
+	/// Strikes of the user since the given time, as their timestamp and the rule they broke, oldest first.
+	pub fn strikes(&self, guild_id: GuildId, user_id: UserId, since: i64) -> rusqlite::Result<Vec<(i64, String)>> {
+		let conn = self.conn.lock().unwrap();
+		let mut stmt = conn.prepare(
+			"SELECT timestamp, rule FROM strikes WHERE guild_id = ?1 AND user_id = ?2 AND timestamp >= ?3
+			ORDER BY timestamp",
+		)?;
+
+		let strikes = stmt.query_map(params![guild_id.get() as i64, user_id.get() as i64, since], |row| {
+			Ok((row.get(0)?, row.get(1)?))
+		})?;
+		strikes.collect()
+	}
+
+	/// Forgets all strikes of the user. Returns how many there were.
+	pub fn clear_strikes(&self, guild_id: GuildId, user_id: UserId) -> rusqlite::Result<usize> {
+		let conn = self.conn.lock().unwrap();
+		conn.execute(
+			"DELETE FROM strikes WHERE guild_id = ?1 AND user_id = ?2",
+			params![guild_id.get() as i64, user_id.get() as i64],
+		)
+	}
+
+	/// Remembers the text of a delivered reminder for its snooze buttons, forgetting the ones delivered before `since`.
+	/// Returns the ID the buttons refer to.
+	pub fn add_snoozable(&self, user_id: UserId, message: &str, now: i64, since: i64) -> rusqlite::Result<i64> {
+		let conn = self.conn.lock().unwrap();
+		conn.execute("DELETE FROM snoozable_reminders WHERE timestamp < ?1", [since])?;
+		conn.execute(
+			"INSERT INTO snoozable_reminders (user_id, message, timestamp) VALUES (?1, ?2, ?3)",
+			params![user_id.get() as i64, message, now],
+		)?;
+		Ok(conn.last_insert_rowid())
+	}
+
+	/// Text of a delivered reminder the user can still snooze.
+	pub fn snoozable(&self, id: i64, user_id: UserId) -> rusqlite::Result<Option<String>> {
+		let conn = self.conn.lock().unwrap();
+		conn.query_row(
+			"SELECT message FROM snoozable_reminders WHERE id = ?1 AND user_id = ?2",
+			params![id, user_id.get() as i64],
+			|row| row.get(0),
+		)
+		.optional()
+	}
+
+	pub fn delete_snoozable(&self, id: i64) -> rusqlite::Result<()> {
+		let conn = self.conn.lock().unwrap();
+		conn.execute("DELETE FROM snoozable_reminders WHERE id = ?1", [id])?;
+		Ok(())
+	}
+
+	/// Puts deleted reminders back, with their original IDs.
+	/// Reminders the user only joined get them back in, if they still exist.
+	/// Reminders that are still there, or got an ID reused, are left as they are.
+	pub fn restore_reminders(&self, user_id: UserId, reminders: &[Reminder]) -> rusqlite::Result<()> {
+		let mut conn = self.conn.lock().unwrap();
+		let tx = conn.transaction()?;
+
+		for reminder in reminders {
+			if reminder.user_id == user_id {
+				insert_reminder_with_id(&tx, reminder)?;
+			} else {
+				tx.execute(
+					"INSERT OR IGNORE INTO reminder_subscribers (reminder_id, user_id)
+					SELECT id, ?2 FROM reminders WHERE id = ?1",
+					params![reminder.id, user_id.get() as i64],
+				)?;
+			}
+		}
+
+		tx.commit()?;
+		self.reminders_changed.notify_one();
+		Ok(())
+	}
+
+	/// Imports the reminders of the old save file all at once, keeping their IDs.
+	pub fn import(&self, reminders: &[Reminder]) -> rusqlite::Result<()> {
+		let mut conn = self.conn.lock().unwrap();
+		let tx = conn.transaction()?;
+
+		for reminder in reminders {
+			insert_reminder_with_id(&tx, reminder)?;
+		}
+
+		tx.commit()?;
+		self.reminders_changed.notify_one();
+		Ok(())
+	}
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+	let version = conn.pragma_query_value(None, "user_version", |row| row.get::<_, usize>(0))?;
+
+	if version > MIGRATIONS.len() {
+		return Err(rusqlite::Error::SqliteFailure(
+			ffi::Error::new(ffi::SQLITE_ERROR),
+			Some("database is from a newer version of AI-chan".to_string()),
+		));
+	}
+
+	for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+		tracing::info!("Migrating database to version {}...", i + 1);
+
+		let tx = conn.transaction()?;
+		tx.execute_batch(migration)?;
+		tx.pragma_update(None, "user_version", i + 1)?;
+		tx.commit()?;
+	}
+
+	Ok(())
+}
+
+/// Inserts the reminder and its subscribers, unless there's already a reminder with its ID.
+fn insert_reminder_with_id(conn: &Connection, reminder: &Reminder) -> rusqlite::Result<()> {
+	let (repeat_every, repeat_weekday, repeat_time) = recurrence_columns(reminder.recurrence);
+	let (post, embed_title, embed_description, announce) = post_columns(&reminder.post);
+
+	let inserted = conn.execute(
+		&format!("INSERT INTO reminders ({REMINDER_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+			ON CONFLICT (id) DO NOTHING"
+		),
+		params![
+			reminder.id,
+			reminder.timestamp,
+			reminder.user_id.get() as i64,
+			reminder.channel_id.get() as i64,
+			reminder.message,
+			repeat_every,
+			repeat_weekday,
+			repeat_time,
+			reminder.deliver.to_byte(),
+			reminder.retry.map_or(0, |retry| retry.attempts),
+			reminder.retry.map(|retry| retry.due),
+			reminder.mentions.to_string(),
+			post,
+			embed_title,
+			embed_description,
+			announce,
+			reminder.guild_id.map(|guild_id| guild_id.get() as i64),
+		],
+	)?;
+
+	if inserted == 0 {
+		return Ok(());
+	}
+
+	conn.execute("DELETE FROM reminder_subscribers WHERE reminder_id = ?1", [reminder.id])?;
+	for subscriber in &reminder.subscribers {
+		conn.execute(
+			"INSERT INTO reminder_subscribers (reminder_id, user_id) VALUES (?1, ?2)",
+			params![reminder.id, subscriber.get() as i64],
+		)?;
+	}
+
+	Ok(())
+}
+
+/// Splits a recurrence into the `repeat_every`, `repeat_weekday` and `repeat_time` columns.
+fn recurrence_columns(recurrence: Option<Recurrence>) -> (Option<i64>, Option<u8>, Option<u32>) {
+	match recurrence {
+		None => (None, None, None),
+		Some(Recurrence::Interval(secs)) => (Some(secs), None, None),
+		Some(Recurrence::Weekly(weekday, time)) => (
+			None,
+			Some(weekday.num_days_from_monday() as u8),
+			Some(time.num_seconds_from_midnight()),
+		),
+	}
+}
+
+/// Splits a scheduled message into the `post`, `post_embed_title`, `post_embed_description` and `post_announce` columns.
+fn post_columns(post: &Option<Post>) -> (bool, Option<&str>, Option<&str>, bool) {
+	match post {
+		None => (false, None, None, false),
+		Some(post) => (
+			true,
+			post.embed_title.as_deref(),
+			post.embed_description.as_deref(),
+			post.announce,
+		),
+	}
+}
+
+fn reminder_from_row(row: &Row) -> rusqlite::Result<Reminder> {
+	let repeat_every = row.get::<_, Option<i64>>(5)?;
+	let repeat_weekday = row.get::<_, Option<u8>>(6)?;
+	let repeat_time = row.get::<_, Option<u32>>(7)?;
+
+	let recurrence = match (repeat_every, repeat_weekday, repeat_time) {
+		(Some(secs), _, _) => Some(Recurrence::Interval(secs)),
+		(None, Some(weekday), Some(secs)) => Weekday::try_from(weekday)
+			.ok()
+			.zip(NaiveTime::from_num_seconds_from_midnight_opt(secs, 0))
+			.map(|(weekday, time)| Recurrence::Weekly(weekday, time)),
+		_ => None,
+	};
+
+	Ok(Reminder {
+		id: row.get(0)?,
+		timestamp: row.get(1)?,
+		user_id: UserId::new(row.get::<_, i64>(2)? as u64),
+		channel_id: ChannelId::new(row.get::<_, i64>(3)? as u64),
+		guild_id: row
+			.get::<_, Option<i64>>(16)?
+			.map(|guild_id| GuildId::new(guild_id as u64)),
+		message: row.get(4)?,
+		recurrence,
+		deliver: Delivery::from_byte(row.get(8)?).unwrap_or_default(),
+		mentions: Mentions::parse(&row.get::<_, String>(11)?).unwrap_or_default(),
+		post: match row.get(12)? {
+			false => None,
+			true => Some(Post {
+				embed_title: row.get(13)?,
+				embed_description: row.get(14)?,
+				announce: row.get(15)?,
+			}),
+		},
+		subscribers: (row.get::<_, Option<String>>(17)?.unwrap_or_default().split_whitespace())
+			.filter_map(|user_id| user_id.parse().ok())
+			.collect(),
+		retry: row.get::<_, Option<i64>>(10)?.map(|due| Retry {
+			attempts: row.get(9).unwrap_or_default(),
+			due,
+		}),
+	})
+}
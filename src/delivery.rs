@@ -0,0 +1,254 @@
+use std::fmt;
+
+use serenity::all::{Context, CreateEmbed, CreateMessage, StatusCode, UserId};
+
+use crate::db::Db;
+use crate::mentions::{channel_permissions, permitted_mentions, Mentions};
+use crate::reminders::{Post, Reminder};
+use crate::snooze;
+
+/// Where a reminder gets sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Delivery {
+	/// In the channel the reminder was made in.
+	#[default]
+	Channel,
+	/// In the user's DMs.
+	Dm,
+	/// Both in the channel and in the user's DMs.
+	Both,
+}
+
+impl Delivery {
+	pub fn from_byte(byte: u8) -> Option<Self> {
+		match byte {
+			0 => Some(Delivery::Channel),
+			1 => Some(Delivery::Dm),
+			2 => Some(Delivery::Both),
+			_ => None,
+		}
+	}
+
+	pub fn to_byte(self) -> u8 {
+		match self {
+			Delivery::Channel => 0,
+			Delivery::Dm => 1,
+			Delivery::Both => 2,
+		}
+	}
+
+	/// Parses the value of a `deliver` command option.
+	pub fn from_option(value: &str) -> Option<Self> {
+		match value {
+			"channel" => Some(Delivery::Channel),
+			"dm" => Some(Delivery::Dm),
+			"both" => Some(Delivery::Both),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for Delivery {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Delivery::Channel => write!(f, "in the channel"),
+			Delivery::Dm => write!(f, "by DM"),
+			Delivery::Both => write!(f, "in the channel and by DM"),
+		}
+	}
+}
+
+/// Where the user wants their reminders to go when they don't say.
+pub fn user_delivery(db: &Db, user_id: UserId) -> Delivery {
+	db.delivery(user_id).unwrap().unwrap_or_default()
+}
+
+/// How sending a reminder went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SendResult {
+	/// It reached the user.
+	Sent,
+	/// It didn't, but it might if tried again later.
+	Failed,
+	/// It never will: the channel is gone or off-limits, and so are the user's DMs.
+	Undeliverable,
+}
+
+/// Whether sending failed for good, like when the channel was deleted,
+/// AI-chan lost access to it, or the user doesn't accept DMs from her.
+fn is_permanent(error: &serenity::Error) -> bool {
+	match error {
+		serenity::Error::Http(e) => e
+			.status_code()
+			.is_some_and(|status| status == StatusCode::FORBIDDEN || status == StatusCode::NOT_FOUND),
+		_ => false,
+	}
+}
+
+/// Sends a reminder where it should go.
+///
+/// If the channel doesn't work out (deleted, or no access anymore), it falls back to DMs, and the other way around.
+///
+/// Only the user, the ones who joined the reminder and the mentions the user is still allowed to make get pinged,
+/// whatever the message says.
+pub async fn send_reminder(ctx: &Context, reminder: &Reminder, content: &str, snoozable_id: Option<i64>) -> SendResult {
+	if let Some(post) = &reminder.post {
+		return send_post(ctx, reminder, post, content).await;
+	}
+
+	let mut content = content.to_string();
+
+	let mut mentions = if reminder.mentions.is_empty() {
+		Mentions::default()
+	} else {
+		let permitted = permitted_mentions(ctx, reminder.user_id, reminder.channel_id, &reminder.mentions).await;
+
+		let forbidden = reminder.mentions.without(&permitted);
+		if !forbidden.is_empty() {
+			content += &format!("\n\n-# I didn't ping {forbidden}, since you're not allowed to anymore~");
+		}
+
+		permitted
+	};
+	mentions.users.extend(&reminder.subscribers);
+
+	let mut builder = CreateMessage::new()
+		.content(&content)
+		.allowed_mentions(mentions.allowed_mentions(reminder.user_id));
+	if let Some(snoozable_id) = snoozable_id {
+		builder = builder.components(snooze::buttons(reminder.user_id, snoozable_id));
+	}
+
+	// errors are whether the failure is permanent
+	let in_channel = || async {
+		match reminder.channel_id.send_message(&ctx.http, builder.clone()).await {
+			Ok(_) => Ok(()),
+			Err(e) => {
+				tracing::warn!(
+					"Cannot send reminder {} in channel {}: {e}",
+					reminder.id,
+					reminder.channel_id
+				);
+				Err(is_permanent(&e))
+			}
+		}
+	};
+
+	let in_dm = |builder: CreateMessage| async move {
+		match reminder.user_id.direct_message(&ctx.http, builder).await {
+			Ok(_) => Ok(()),
+			Err(e) => {
+				tracing::warn!(
+					"Cannot send reminder {} to {} by DM: {e}",
+					reminder.id,
+					reminder.user_id
+				);
+				Err(is_permanent(&e))
+			}
+		}
+	};
+
+	let result = match reminder.deliver {
+		Delivery::Channel => match in_channel().await {
+			Ok(()) => Ok(()),
+			Err(channel_permanent) => {
+				let builder = if channel_permanent {
+					builder.clone().content(format!(
+						"{content}\n\n-# I can't send messages in <#{}> anymore, so here it is~",
+						reminder.channel_id
+					))
+				} else {
+					builder.clone()
+				};
+
+				(in_dm(builder).await).map_err(|dm_permanent| channel_permanent && dm_permanent)
+			}
+		},
+		Delivery::Dm => match in_dm(builder.clone()).await {
+			Ok(()) => Ok(()),
+			Err(dm_permanent) => (in_channel().await).map_err(|channel_permanent| dm_permanent && channel_permanent),
+		},
+		Delivery::Both => match (in_channel().await, in_dm(builder.clone()).await) {
+			(Err(channel_permanent), Err(dm_permanent)) => Err(channel_permanent && dm_permanent),
+			_ => Ok(()),
+		},
+	};
+
+	match result {
+		Ok(()) => SendResult::Sent,
+		Err(false) => SendResult::Failed,
+		Err(true) => SendResult::Undeliverable,
+	}
+}
+
+/// Posts a message scheduled with `/schedule` in its channel, as long as its author can still manage messages there.
+///
+/// Unlike reminders, it never goes to DMs, and only pings the mentions the author is still allowed to make.
+async fn send_post(ctx: &Context, reminder: &Reminder, post: &Post, content: &str) -> SendResult {
+	match channel_permissions(ctx, reminder.user_id, reminder.channel_id).await {
+		Ok(Some((permissions, _))) if permissions.manage_messages() => {}
+		Ok(_) => {
+			tracing::warn!(
+				"Dropping scheduled message {}: {} can't manage messages in channel {} anymore",
+				reminder.id,
+				reminder.user_id,
+				reminder.channel_id
+			);
+			return SendResult::Undeliverable;
+		}
+		Err(e) => {
+			tracing::warn!(
+				"Cannot check permissions of {} for scheduled message {}: {e}",
+				reminder.user_id,
+				reminder.id
+			);
+			return if is_permanent(&e) {
+				SendResult::Undeliverable
+			} else {
+				SendResult::Failed
+			};
+		}
+	}
+
+	let mentions = permitted_mentions(ctx, reminder.user_id, reminder.channel_id, &reminder.mentions).await;
+
+	let mut builder = CreateMessage::new()
+		.content(content)
+		.allowed_mentions(mentions.allowed_mentions(reminder.user_id));
+
+	if post.embed_title.is_some() || post.embed_description.is_some() {
+		let mut embed = CreateEmbed::new();
+		if let Some(title) = &post.embed_title {
+			embed = embed.title(title);
+		}
+		if let Some(description) = &post.embed_description {
+			embed = embed.description(description);
+		}
+		builder = builder.embed(embed);
+	}
+
+	let message = match reminder.channel_id.send_message(&ctx.http, builder).await {
+		Ok(message) => message,
+		Err(e) => {
+			tracing::warn!(
+				"Cannot send scheduled message {} in channel {}: {e}",
+				reminder.id,
+				reminder.channel_id
+			);
+			return if is_permanent(&e) {
+				SendResult::Undeliverable
+			} else {
+				SendResult::Failed
+			};
+		}
+	};
+
+	if post.announce {
+		// it's out already, so a failed crosspost isn't worth sending it again
+		if let Err(e) = message.crosspost(&ctx.http).await {
+			tracing::warn!("Cannot publish scheduled message {}: {e}", reminder.id);
+		}
+	}
+
+	SendResult::Sent
+}
@@ -0,0 +1,73 @@
+use serenity::all::{
+	ButtonStyle, ComponentInteraction, Context, CreateActionRow, CreateButton, CreateInteractionResponse,
+	CreateInteractionResponseMessage,
+};
+
+use crate::db::Db;
+
+/// Prefix of the custom IDs of the buttons to join or leave a group reminder, followed by `{action}:{reminder id}`.
+pub const CUSTOM_ID_PREFIX: &str = "group:";
+
+/// How many people can join a reminder. Each of them gets pinged in it, and Discord only takes
+/// messages up to 2000 characters pinging up to 100 users.
+const MAX_SUBSCRIBERS: usize = 20;
+
+/// Buttons to attach to the confirmation of a group reminder, so others can join it.
+pub fn buttons(reminder_id: i64) -> Vec<CreateActionRow> {
+	let button =
+		|action: &str, label: &str| CreateButton::new(format!("{CUSTOM_ID_PREFIX}{action}:{reminder_id}")).label(label);
+
+	vec![CreateActionRow::Buttons(vec![
+		button("join", "Remind me too").style(ButtonStyle::Primary),
+		button("leave", "Leave").style(ButtonStyle::Secondary),
+	])]
+}
+
+pub async fn handle_component(db: &Db, ctx: &Context, component: &ComponentInteraction) {
+	let custom_id = &component.data.custom_id;
+	let Some((action, reminder_id)) = (custom_id.strip_prefix(CUSTOM_ID_PREFIX))
+		.and_then(|rest| rest.split_once(':'))
+		.and_then(|(action, id)| Some((action, id.parse::<i64>().ok()?)))
+	else {
+		tracing::error!("Invalid group reminder button {custom_id:?}");
+		return;
+	};
+
+	let user_id = component.user.id;
+	let reminder = db.user_reminder(user_id, reminder_id).unwrap();
+
+	let content = match (action, reminder) {
+		("join", Some(rem)) if rem.user_id == user_id => "This is your own reminder, silly~".to_string(),
+		("join", Some(rem)) => format!("You're already in! I'll remind you <t:{}:R>~", rem.timestamp),
+		("join", None) => match db.subscribe(reminder_id, user_id, MAX_SUBSCRIBERS).unwrap() {
+			true => {
+				let timestamp = db.user_reminder(user_id, reminder_id).unwrap().map(|rem| rem.timestamp);
+				match timestamp {
+					Some(timestamp) => format!("Okie, I'll remind you too <t:{timestamp}:R>~"),
+					None => "Okie, I'll remind you too~".to_string(),
+				}
+			}
+			false if db.count_subscribers(reminder_id).unwrap() >= MAX_SUBSCRIBERS => {
+				format!("This reminder is full, {MAX_SUBSCRIBERS} people joined it already :c")
+			}
+			false => "This reminder is gone :c".to_string(),
+		},
+		("leave", Some(rem)) if rem.user_id == user_id => {
+			"You made this one! Delete it with /myreminders if you don't need it anymore~".to_string()
+		}
+		("leave", _) => match db.unsubscribe(reminder_id, user_id).unwrap() {
+			true => "Okie, I won't remind you about this one~".to_string(),
+			false => "You weren't in this one anyway~".to_string(),
+		},
+		(action, _) => {
+			tracing::error!("Invalid group reminder action {action:?}");
+			return;
+		}
+	};
+
+	let builder =
+		CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(content).ephemeral(true));
+	if let Err(e) = component.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to button: {e}");
+	}
+}
@@ -0,0 +1,515 @@
+//! Reminders to and from iCalendar (`.ics`) files, so they show up in calendar apps.
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::reminders::{excerpt, Recurrence, Reminder, MAX_REPEAT_SECS};
+use crate::timezones::local_timestamp;
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Longest a line can be before it's folded, in bytes.
+const MAX_LINE_LEN: usize = 75;
+
+const ICS_DATE_TIME: &str = "%Y%m%dT%H%M%S";
+
+/// 9999-12-31T23:59:59Z, the latest time a calendar can write. Imported times stay within it.
+const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+/// A calendar with an event and an alarm for each reminder, repeating like they do.
+///
+/// Times are written in UTC, as calendars need a `VTIMEZONE` to know about any other time zone.
+pub fn export(reminders: &[Reminder], now: i64) -> String {
+	let mut lines = vec![
+		"BEGIN:VCALENDAR".to_string(),
+		"VERSION:2.0".to_string(),
+		"PRODID:-//AI-chan//Reminders//EN".to_string(),
+		"CALSCALE:GREGORIAN".to_string(),
+	];
+
+	for rem in reminders {
+		let summary = escape(&excerpt(&rem.message, 80));
+
+		lines.push("BEGIN:VEVENT".to_string());
+		lines.push(format!("UID:reminder-{}@ai-chan", rem.id));
+		lines.push(format!("DTSTAMP:{}", utc_date_time(now)));
+		lines.push(format!("DTSTART:{}", utc_date_time(rem.timestamp)));
+		lines.push(format!("SUMMARY:{summary}"));
+		lines.push(format!("DESCRIPTION:{}", escape(&rem.message)));
+
+		if let Some(recurrence) = rem.recurrence {
+			lines.push(format!("RRULE:{}", rrule(recurrence)));
+		}
+
+		lines.push("BEGIN:VALARM".to_string());
+		lines.push("ACTION:DISPLAY".to_string());
+		lines.push(format!("DESCRIPTION:{summary}"));
+		lines.push("TRIGGER:PT0S".to_string());
+		lines.push("END:VALARM".to_string());
+		lines.push("END:VEVENT".to_string());
+	}
+
+	lines.push("END:VCALENDAR".to_string());
+
+	lines.iter().map(|line| fold(line)).collect::<Vec<_>>().join("")
+}
+
+fn utc_date_time(timestamp: i64) -> String {
+	DateTime::from_timestamp(timestamp, 0)
+		.unwrap()
+		.format("%Y%m%dT%H%M%SZ")
+		.to_string()
+}
+
+fn rrule(recurrence: Recurrence) -> String {
+	match recurrence {
+		Recurrence::Interval(secs) => {
+			let (freq, unit) = [("DAILY", DAY_SECS), ("HOURLY", 60 * 60), ("MINUTELY", 60)]
+				.into_iter()
+				.find(|(_, unit)| secs % unit == 0)
+				.unwrap_or(("SECONDLY", 1));
+
+			format!("FREQ={freq};INTERVAL={}", secs / unit)
+		}
+		// the start is on the right day in the user's time zone, but not necessarily in UTC
+		Recurrence::Weekly(..) => "FREQ=WEEKLY".to_string(),
+	}
+}
+
+fn escape(text: &str) -> String {
+	text.replace('\\', "\\\\")
+		.replace(';', "\\;")
+		.replace(',', "\\,")
+		.replace('\n', "\\n")
+		.replace('\r', "")
+}
+
+fn unescape(text: &str) -> String {
+	let mut unescaped = String::with_capacity(text.len());
+	let mut chars = text.chars();
+
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => match chars.next() {
+				Some('n' | 'N') => unescaped.push('\n'),
+				Some(c) => unescaped.push(c),
+				None => {}
+			},
+			c => unescaped.push(c),
+		}
+	}
+
+	unescaped
+}
+
+/// Splits a line into lines of at most [`MAX_LINE_LEN`] bytes, continued with a space, without cutting characters.
+fn fold(line: &str) -> String {
+	let mut folded = String::with_capacity(line.len() + 2);
+	let mut line_len = 0;
+
+	for c in line.chars() {
+		if line_len + c.len_utf8() > MAX_LINE_LEN {
+			folded += "\r\n ";
+			line_len = 1;
+		}
+
+		folded.push(c);
+		line_len += c.len_utf8();
+	}
+
+	folded + "\r\n"
+}
+
+/// An event of an imported calendar, as a reminder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IcsEvent {
+	pub timestamp: i64,
+	pub message: String,
+	pub recurrence: Option<Recurrence>,
+	/// Whether it had a repeat rule AI-chan doesn't understand, so it only goes off once.
+	pub unsupported_repeat: bool,
+}
+
+/// What could be read from a calendar.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportedEvents {
+	pub events: Vec<IcsEvent>,
+	/// Events that are already over and don't repeat.
+	pub past: usize,
+	/// Events without a start time, or with a broken one or alarm.
+	pub invalid: usize,
+}
+
+/// Reads the events of a calendar, moving repeating ones to their next occurrence after `now`.
+///
+/// Events go off at their first alarm when they have one, and at their start otherwise.
+/// Times without a time zone are in `tz`, which repeat rules are also followed in.
+pub fn import(text: &str, tz: Tz, now: i64) -> Result<ImportedEvents, &'static str> {
+	let lines = unfold(text.trim_start_matches('\u{feff}'));
+	if lines
+		.first()
+		.is_none_or(|line| !line.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+	{
+		return Err("This isn't an iCalendar file :c");
+	}
+
+	let mut imported = ImportedEvents::default();
+	let mut event: Option<Vec<&str>> = None;
+
+	for line in &lines {
+		match line.to_ascii_uppercase().as_str() {
+			"BEGIN:VEVENT" => event = Some(Vec::new()),
+			"END:VEVENT" => match event.take().and_then(|event| read_event(&event, tz, now)) {
+				Some(Some(event)) => imported.events.push(event),
+				Some(None) => imported.past += 1,
+				None => imported.invalid += 1,
+			},
+			_ => {
+				if let Some(event) = &mut event {
+					event.push(line);
+				}
+			}
+		}
+	}
+
+	Ok(imported)
+}
+
+/// Joins the lines that were folded, dropping empty ones.
+fn unfold(text: &str) -> Vec<String> {
+	let mut lines: Vec<String> = Vec::new();
+
+	for line in text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)) {
+		match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+			(Some(continued), Some(last)) => *last += continued,
+			_ if line.is_empty() => {}
+			_ => lines.push(line.to_string()),
+		}
+	}
+
+	lines
+}
+
+/// Parameters of a content line, with their names in uppercase.
+type Params<'a> = Vec<(String, &'a str)>;
+
+/// Splits a content line into its name, parameters and value.
+fn split_property(line: &str) -> Option<(String, Params<'_>, &str)> {
+	let (head, value) = line.split_once(':')?;
+	let mut parts = head.split(';');
+	let name = parts.next()?.to_ascii_uppercase();
+
+	let params = parts
+		.filter_map(|param| param.split_once('='))
+		.map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"')))
+		.collect();
+
+	Some((name, params, value))
+}
+
+/// None if the event is broken, Some(None) if it's over and doesn't repeat.
+fn read_event(lines: &[&str], tz: Tz, now: i64) -> Option<Option<IcsEvent>> {
+	let mut start = None;
+	let mut summary = None;
+	let mut description = None;
+	let mut rrule = None;
+	let mut trigger = None;
+	let mut in_alarm = false;
+
+	for line in lines {
+		let Some((name, params, value)) = split_property(line) else {
+			continue;
+		};
+
+		match (name.as_str(), in_alarm) {
+			("BEGIN", _) if value.eq_ignore_ascii_case("VALARM") => in_alarm = true,
+			("END", _) if value.eq_ignore_ascii_case("VALARM") => in_alarm = false,
+			("TRIGGER", true) if trigger.is_none() => {
+				// only alarms relative to the start are understood
+				let related_to_end =
+					(params.iter()).any(|(key, value)| key == "RELATED" && value.eq_ignore_ascii_case("END"));
+				// neither are alarms at a set time, which are just ignored
+				let is_duration = value.trim_start_matches(['-', '+']).starts_with(['P', 'p']);
+				if !related_to_end && is_duration {
+					trigger = Some(parse_duration(value)?);
+				}
+			}
+			("DTSTART", false) => start = Some(parse_date_time(&params, value, tz)?),
+			("SUMMARY", false) => summary = Some(unescape(value)),
+			("DESCRIPTION", false) => description = Some(unescape(value)),
+			("RRULE", false) => rrule = Some(value.to_ascii_uppercase()),
+			_ => {}
+		}
+	}
+
+	let timestamp = (start?.checked_add(trigger.unwrap_or(0)))
+		.filter(|timestamp| DateTime::from_timestamp(*timestamp, 0).is_some() && *timestamp <= MAX_TIMESTAMP)?;
+
+	let message = match (summary, description) {
+		(Some(summary), Some(description)) if !description.trim().is_empty() && description != summary => {
+			format!("{summary}\n\n{description}")
+		}
+		(Some(summary), _) => summary,
+		(None, Some(description)) => description,
+		(None, None) => "(no title)".to_string(),
+	};
+
+	let recurrence = rrule.as_deref().map(|rrule| parse_rrule(rrule, timestamp, tz));
+	let unsupported_repeat = recurrence.is_some_and(|recurrence| recurrence.is_none());
+
+	let (timestamp, recurrence) = match recurrence.flatten() {
+		Some(recurrence) if timestamp <= now => (recurrence.next_after(timestamp, now, tz)?, Some(recurrence)),
+		_ if timestamp <= now => return Some(None),
+		recurrence => (timestamp, recurrence),
+	};
+
+	Some(Some(IcsEvent {
+		timestamp,
+		message,
+		recurrence,
+		unsupported_repeat,
+	}))
+}
+
+/// Timestamp of a `DATE-TIME` or `DATE` value. All-day events start at midnight in `tz`.
+fn parse_date_time(params: &[(String, &str)], value: &str, tz: Tz) -> Option<i64> {
+	if let Some(utc) = value.strip_suffix(['Z', 'z']) {
+		let date_time = NaiveDateTime::parse_from_str(utc, ICS_DATE_TIME).ok()?;
+		return Some(Utc.from_utc_datetime(&date_time).timestamp());
+	}
+
+	let date_tz = (params.iter())
+		.find(|(key, _)| key == "TZID")
+		.and_then(|(_, tzid)| tzid.parse::<Tz>().ok())
+		.unwrap_or(tz);
+
+	let date_time = match NaiveDateTime::parse_from_str(value, ICS_DATE_TIME) {
+		Ok(date_time) => date_time,
+		Err(_) => NaiveDate::parse_from_str(value, "%Y%m%d")
+			.ok()?
+			.and_time(NaiveTime::MIN),
+	};
+
+	Some(local_timestamp(date_tz, date_time))
+}
+
+/// Seconds of a duration like `-PT15M` or `P1DT2H`.
+fn parse_duration(value: &str) -> Option<i64> {
+	let (sign, value) = match value.strip_prefix('-') {
+		Some(value) => (-1, value),
+		None => (1, value.strip_prefix('+').unwrap_or(value)),
+	};
+
+	let mut secs: i64 = 0;
+	let mut number = String::new();
+	let mut in_time = false;
+
+	for c in value.strip_prefix(['P', 'p'])?.chars() {
+		match c.to_ascii_uppercase() {
+			'0'..='9' => number.push(c),
+			'T' => in_time = true,
+			unit => {
+				let n = number.parse::<i64>().ok()?;
+				number.clear();
+
+				let unit_secs = match (unit, in_time) {
+					('W', false) => 7 * DAY_SECS,
+					('D', false) => DAY_SECS,
+					('H', true) => 60 * 60,
+					('M', true) => 60,
+					('S', true) => 1,
+					_ => return None,
+				};
+				secs = n.checked_mul(unit_secs).and_then(|n| secs.checked_add(n))?;
+			}
+		}
+	}
+
+	number.is_empty().then_some(sign * secs)
+}
+
+/// The recurrence of a repeat rule, or None if it can't be followed exactly,
+/// like monthly ones, ones with an end, or ones with several days.
+fn parse_rrule(rrule: &str, first: i64, tz: Tz) -> Option<Recurrence> {
+	let mut freq = None;
+	let mut interval = 1;
+	let mut by_day = None;
+
+	for part in rrule.split(';') {
+		let (key, value) = part.split_once('=')?;
+		match key {
+			"FREQ" => freq = Some(value),
+			"INTERVAL" => interval = value.parse::<i64>().ok().filter(|&interval| interval > 0)?,
+			"BYDAY" => by_day = Some(value),
+			// "every week from monday" is the same as "every monday"
+			"WKST" => {}
+			_ => return None,
+		}
+	}
+
+	let unit = match freq? {
+		"WEEKLY" if interval == 1 => {
+			let first = tz.from_utc_datetime(&DateTime::from_timestamp(first, 0)?.naive_utc());
+			let weekday = match by_day {
+				None => first.weekday(),
+				Some(day) => parse_ics_weekday(day)?,
+			};
+			let time = NaiveTime::from_hms_opt(first.hour(), first.minute(), 0)?;
+
+			return Some(Recurrence::Weekly(weekday, time));
+		}
+		_ if by_day.is_some() => return None,
+		"WEEKLY" => 7 * DAY_SECS,
+		"DAILY" => DAY_SECS,
+		"HOURLY" => 60 * 60,
+		"MINUTELY" => 60,
+		"SECONDLY" => 1,
+		_ => return None,
+	};
+
+	// same minimum as /remindme
+	(interval.checked_mul(unit))
+		.filter(|&secs| (60..=MAX_REPEAT_SECS).contains(&secs))
+		.map(Recurrence::Interval)
+}
+
+fn parse_ics_weekday(day: &str) -> Option<Weekday> {
+	match day {
+		"MO" => Some(Weekday::Mon),
+		"TU" => Some(Weekday::Tue),
+		"WE" => Some(Weekday::Wed),
+		"TH" => Some(Weekday::Thu),
+		"FR" => Some(Weekday::Fri),
+		"SA" => Some(Weekday::Sat),
+		"SU" => Some(Weekday::Sun),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serenity::all::{ChannelId, UserId};
+
+	use super::*;
+	use crate::delivery::Delivery;
+	use crate::mentions::Mentions;
+
+	fn reminder(id: i64, timestamp: i64, message: &str, recurrence: Option<Recurrence>) -> Reminder {
+		Reminder {
+			id,
+			timestamp,
+			user_id: UserId::new(1),
+			channel_id: ChannelId::new(2),
+			guild_id: None,
+			message: message.to_string(),
+			recurrence,
+			deliver: Delivery::Channel,
+			mentions: Mentions::default(),
+			subscribers: Vec::new(),
+			post: None,
+			retry: None,
+		}
+	}
+
+	#[test]
+	fn exported_reminders_import_back() {
+		let tz = chrono_tz::Europe::Paris;
+		// monday 2027-01-18 at 00:30 in Paris, still sunday in UTC
+		let monday = 1_800_228_600;
+		let now = 1_800_000_000;
+		let weekly = Recurrence::Weekly(Weekday::Mon, NaiveTime::from_hms_opt(0, 30, 0).unwrap());
+		let long_message = "Water the plants; the big ones, then the small ones.\nDon't forget the cactus! ".repeat(3);
+
+		let reminders = [
+			reminder(1, now + 3600, &long_message, None),
+			reminder(2, now + 60, "Stretch", Some(Recurrence::Interval(2 * DAY_SECS))),
+			reminder(3, monday, "Standup", Some(weekly)),
+		];
+
+		let ics = export(&reminders, now);
+		assert!(ics.lines().all(|line| line.len() <= MAX_LINE_LEN + 1));
+		assert!(ics.contains("RRULE:FREQ=DAILY;INTERVAL=2\r\n"));
+		assert!(ics.contains("RRULE:FREQ=WEEKLY\r\n"));
+		assert!(ics.contains("DTSTART:20270117T233000Z\r\n"));
+		assert!(!ics.contains("TZID"));
+
+		let imported = import(&ics, tz, now).unwrap();
+		assert_eq!((imported.past, imported.invalid), (0, 0));
+
+		let events = imported.events;
+		assert_eq!(events.len(), 3);
+		assert_eq!(events[0].timestamp, now + 3600);
+		assert!(events[0].message.ends_with(&long_message));
+		assert_eq!(events[1].recurrence, Some(Recurrence::Interval(2 * DAY_SECS)));
+		assert_eq!(events[1].message, "Stretch");
+		assert_eq!((events[2].timestamp, events[2].recurrence), (monday, Some(weekly)));
+	}
+
+	#[test]
+	fn import_alarms_rules_and_past_events() {
+		let now = 1_800_000_000; // 2027-01-15 08:00:00 UTC, a friday
+		let ics = "BEGIN:VCALENDAR\r\n\
+			BEGIN:VEVENT\r\n\
+			DTSTART:20270120T100000Z\r\n\
+			SUMMARY:Dentist\r\n\
+			BEGIN:VALARM\r\n\
+			TRIGGER:-PT30M\r\n\
+			END:VALARM\r\n\
+			END:VEVENT\r\n\
+			BEGIN:VEVENT\r\n\
+			DTSTART;TZID=America/New_York:20270101T090000\r\n\
+			SUMMARY:Gym\r\n\
+			RRULE:FREQ=WEEKLY;BYDAY=TU\r\n\
+			END:VEVENT\r\n\
+			BEGIN:VEVENT\r\n\
+			DTSTART;VALUE=DATE:20270301\r\n\
+			SUMMARY:Rent\r\n\
+			RRULE:FREQ=MONTHLY\r\n\
+			END:VEVENT\r\n\
+			BEGIN:VEVENT\r\n\
+			DTSTART:20260101T000000Z\r\n\
+			SUMMARY:Over\r\n\
+			END:VEVENT\r\n\
+			BEGIN:VEVENT\r\n\
+			SUMMARY:No start\r\n\
+			END:VEVENT\r\n\
+			END:VCALENDAR\r\n";
+
+		let imported = import(ics, chrono_tz::America::New_York, now).unwrap();
+		assert_eq!((imported.past, imported.invalid), (1, 1));
+
+		let events = imported.events;
+		assert_eq!(events[0].timestamp, 1_800_439_200 - 30 * 60);
+		assert_eq!(
+			events[1].recurrence,
+			Some(Recurrence::Weekly(
+				Weekday::Tue,
+				NaiveTime::from_hms_opt(9, 0, 0).unwrap()
+			))
+		);
+		// next tuesday at 9 in New York
+		assert_eq!(events[1].timestamp, 1_800_367_200);
+		assert!(events[2].unsupported_repeat);
+		assert_eq!(events[2].recurrence, None);
+
+		assert!(import("hello", Tz::UTC, now).is_err());
+	}
+
+	#[test]
+	fn import_oversized_durations_and_repeats() {
+		let now = 1_800_000_000;
+		let event = |lines: &str| format!("BEGIN:VEVENT\r\nDTSTART:20270120T100000Z\r\n{lines}END:VEVENT\r\n");
+		let ics = [
+			"BEGIN:VCALENDAR\r\n".to_string(),
+			event("BEGIN:VALARM\r\nTRIGGER:-P99999999999999W\r\nEND:VALARM\r\n"),
+			event("BEGIN:VALARM\r\nTRIGGER:P9999999999999D\r\nEND:VALARM\r\n"),
+			event("RRULE:FREQ=WEEKLY;INTERVAL=99999999999999\r\n"),
+			"END:VCALENDAR\r\n".to_string(),
+		]
+		.concat();
+
+		let imported = import(&ics, Tz::UTC, now).unwrap();
+		assert_eq!((imported.past, imported.invalid), (0, 2));
+		assert!(imported.events[0].unsupported_repeat);
+	}
+}
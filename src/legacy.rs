@@ -0,0 +1,209 @@
+//! Reader for the reminders file AI-chan used before the SQLite database, only kept to import it once.
+
+use std::{
+	fs::{self, File},
+	io::{self, BufReader, Read},
+	path::Path,
+};
+
+use serenity::all::{ChannelId, UserId};
+
+use crate::db::Db;
+use crate::delivery::Delivery;
+use crate::mentions::Mentions;
+use crate::reminders::Reminder;
+
+const REMINDERS_FILE_NAME: &str = "ai-chan-reminders.bin";
+
+/// Longest string a record can hold. Anything longer means the length itself is garbage.
+const MAX_STRING_LEN: usize = 1 << 20;
+
+/// Imports the old reminders file into the database if there is one,
+/// then renames it so it doesn't get imported again.
+///
+/// Corrupt records are skipped and reported rather than failing the whole import,
+/// the renamed file is still around to recover them by hand.
+/// If nothing at all could be read from it, it's left as it is.
+pub fn import_legacy_reminders(db: &Db) -> io::Result<()> {
+	if !Path::new(REMINDERS_FILE_NAME).exists() {
+		return Ok(());
+	}
+
+	let reminders = match load_reminders() {
+		Ok(reminders) => reminders,
+		Err(e) => {
+			tracing::error!("Cannot import {REMINDERS_FILE_NAME}, leaving it as it is: {e}");
+			return Ok(());
+		}
+	};
+
+	db.import(&reminders).map_err(io::Error::other)?;
+	tracing::info!("Imported {} reminders from the old save file", reminders.len());
+
+	fs::rename(REMINDERS_FILE_NAME, format!("{REMINDERS_FILE_NAME}.imported"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads a Discord ID, which is never 0, so a 0 only spoils this record.
+fn read_id(r: &mut impl Read) -> io::Result<Result<u64, &'static str>> {
+	let mut id_bytes = [0_u8; 8];
+	r.read_exact(&mut id_bytes)?;
+
+	match u64::from_le_bytes(id_bytes) {
+		0 => Ok(Err("ID is 0")),
+		id => Ok(Ok(id)),
+	}
+}
+
+/// Reads a string prefixed by its length.
+///
+/// Invalid UTF-8 only spoils this string, the reader is still at the start of the next field.
+fn read_string(r: &mut impl Read) -> io::Result<Result<String, &'static str>> {
+	let mut len_bytes = [0_u8; 8];
+	r.read_exact(&mut len_bytes)?;
+	let len = u64::from_le_bytes(len_bytes) as usize;
+
+	if len > MAX_STRING_LEN {
+		return Err(invalid_data("string length out of range"));
+	}
+
+	let mut bytes = vec![0_u8; len];
+	r.read_exact(bytes.as_mut_slice())?;
+
+	Ok(String::from_utf8(bytes).map_err(|_| "invalid UTF-8"))
+}
+
+/// Reads a reminder.
+///
+/// The outer error means the file can't be read any further,
+/// the inner one means only this reminder is corrupt and the next one can still be read.
+fn read_reminder(r: &mut impl Read) -> io::Result<Result<Reminder, &'static str>> {
+	let mut id_bytes = [0_u8; 8];
+	r.read_exact(&mut id_bytes)?;
+	let id = i64::from_le_bytes(id_bytes);
+
+	let mut timestamp_bytes = [0_u8; 8];
+	r.read_exact(&mut timestamp_bytes)?;
+	let timestamp = i64::from_le_bytes(timestamp_bytes);
+
+	let user_id = read_id(r)?;
+	let channel_id = read_id(r)?;
+
+	let message = read_string(r)?;
+
+	Ok(user_id.and_then(|user_id| {
+		Ok(Reminder {
+			id,
+			timestamp,
+			user_id: UserId::new(user_id),
+			channel_id: ChannelId::new(channel_id?),
+			guild_id: None,
+			message: message?,
+			recurrence: None,
+			deliver: Delivery::Channel,
+			mentions: Mentions::default(),
+			subscribers: Vec::new(),
+			post: None,
+			retry: None,
+		})
+	}))
+}
+
+/// Reads the reminders of the file, failing if it has some but none of them could be read.
+fn load_reminders() -> io::Result<Vec<Reminder>> {
+	let mut r = BufReader::new(File::open(REMINDERS_FILE_NAME)?);
+	read_reminders(&mut r)
+}
+
+fn read_reminders(r: &mut impl Read) -> io::Result<Vec<Reminder>> {
+	let mut reminders_len_bytes = [0_u8; 8];
+	r.read_exact(&mut reminders_len_bytes)?;
+	let reminders_len = u64::from_le_bytes(reminders_len_bytes);
+
+	let mut reminders = Vec::new();
+	for i in 0..reminders_len {
+		match read_reminder(r) {
+			Ok(Ok(reminder)) => reminders.push(reminder),
+			Ok(Err(reason)) => tracing::error!("Skipping corrupt reminder #{i} of {REMINDERS_FILE_NAME}: {reason}"),
+			Err(e) if reminders.is_empty() => return Err(e),
+			Err(e) => {
+				tracing::error!(
+					"Cannot read {REMINDERS_FILE_NAME} past reminder #{i}, skipping the last {} reminders: {e}",
+					reminders_len - i
+				);
+				break;
+			}
+		}
+	}
+
+	if reminders_len > 0 && reminders.is_empty() {
+		return Err(invalid_data("none of the reminders could be read"));
+	}
+
+	Ok(reminders)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use super::{read_reminder, read_reminders};
+
+	fn reminder_bytes(id: i64, user_id: u64, message: &[u8]) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		bytes.extend(id.to_le_bytes());
+		bytes.extend(1_700_000_000_i64.to_le_bytes());
+		bytes.extend(user_id.to_le_bytes());
+		bytes.extend(42_u64.to_le_bytes());
+		bytes.extend((message.len() as u64).to_le_bytes());
+		bytes.extend(message);
+		bytes
+	}
+
+	#[test]
+	fn corrupt_reminders_are_skipped() {
+		let mut bytes = reminder_bytes(1, 7, b"\xff\xfe not UTF-8");
+		bytes.extend(reminder_bytes(2, 0, b"no user"));
+		bytes.extend(reminder_bytes(3, 7, "fine ♡".as_bytes()));
+
+		let mut r = Cursor::new(bytes);
+		let results = (0..3).map(|_| read_reminder(&mut r).unwrap()).collect::<Vec<_>>();
+
+		assert_eq!(results[0].as_ref().err(), Some(&"invalid UTF-8"));
+		assert_eq!(results[1].as_ref().err(), Some(&"ID is 0"));
+
+		let reminder = results[2].as_ref().unwrap();
+		assert_eq!((reminder.id, reminder.message.as_str()), (3, "fine ♡"));
+	}
+
+	#[test]
+	fn garbage_lengths_stop_reading() {
+		let mut bytes = reminder_bytes(1, 7, b"hi");
+		bytes[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+		assert!(read_reminder(&mut Cursor::new(bytes)).is_err());
+
+		let bytes = reminder_bytes(1, 7, b"cut short");
+		assert!(read_reminder(&mut Cursor::new(&bytes[..30])).is_err());
+	}
+
+	#[test]
+	fn files_without_any_readable_reminder_fail() {
+		let file = |count: u64, records: &[Vec<u8>]| {
+			let mut bytes = count.to_le_bytes().to_vec();
+			bytes.extend(records.concat());
+			read_reminders(&mut Cursor::new(bytes))
+		};
+
+		assert!(file(0, &[]).unwrap().is_empty());
+		assert!(file(1, &[reminder_bytes(1, 0, b"no user")]).is_err());
+		assert!(file(2, &[reminder_bytes(1, 0, b"no user")]).is_err());
+		assert!(read_reminders(&mut Cursor::new(b"AIREMIND")).is_err());
+
+		// what could be read is still imported
+		let reminders = file(2, &[reminder_bytes(1, 7, b"fine")]).unwrap();
+		assert_eq!(reminders.len(), 1);
+	}
+}
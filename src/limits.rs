@@ -0,0 +1,243 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use serenity::all::UserId;
+
+use crate::db::Db;
+use crate::reminders::Reminder;
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// How much each user and each server can ask of AI-chan, so nobody can fill her database up.
+pub struct Limits {
+	/// Most reminders a user can have waiting at once.
+	pub max_reminders_per_user: usize,
+	/// Most reminders that can be waiting at once in a single server, all users together.
+	pub max_reminders_per_guild: usize,
+	/// Longest message a reminder can have, in characters.
+	pub max_message_len: usize,
+	/// Furthest into the future a reminder can go off.
+	pub max_future_secs: i64,
+	/// How many times a user can make reminders within `rate_window_secs`. An import counts once.
+	pub max_created_per_window: usize,
+	pub rate_window_secs: i64,
+	/// When each user last made reminders, within the rate window.
+	recently_created: Mutex<HashMap<UserId, VecDeque<i64>>>,
+}
+
+/// Why a reminder can't be made.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LimitError {
+	TooManyReminders { max: usize },
+	TooManyInGuild { max: usize },
+	MessageTooLong { len: usize, max: usize },
+	TooFarAhead { max_secs: i64 },
+	TooFast { retry_at: i64 },
+}
+
+/// Explains which limit was hit, and what to do about it.
+pub fn error_message(e: &LimitError) -> String {
+	match e {
+		LimitError::TooManyReminders { max } => format!(
+			"You can only have {max} reminders at once, and that would be too many! Delete some with /myreminders first~"
+		),
+		LimitError::TooManyInGuild { max } => {
+			format!("This server already has {max} reminders waiting, I can't keep track of any more :c")
+		}
+		LimitError::MessageTooLong { len, max } => {
+			format!("That's a long message! I can only remember {max} characters, and yours has {len} :c")
+		}
+		LimitError::TooFarAhead { max_secs } => format!(
+			"That's way too far away! I only take reminders up to {} days ahead~",
+			max_secs / DAY_SECS
+		),
+		LimitError::TooFast { retry_at } => {
+			format!("Slow down, sweetie! You can make more reminders <t:{retry_at}:R>~")
+		}
+	}
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Self {
+			max_reminders_per_user: 100,
+			max_reminders_per_guild: 5000,
+			// leaves room for the ping and the footer in Discord's 2000 characters
+			max_message_len: 1500,
+			max_future_secs: 5 * 365 * DAY_SECS,
+			max_created_per_window: 10,
+			rate_window_secs: 60,
+			recently_created: Mutex::default(),
+		}
+	}
+}
+
+impl Limits {
+	/// The default limits, overridden by the `MAX_REMINDERS_PER_USER`, `MAX_REMINDERS_PER_GUILD`,
+	/// `MAX_MESSAGE_LEN`, `MAX_FUTURE_DAYS`, `RATE_LIMIT_COUNT` and `RATE_LIMIT_SECS` env vars.
+	pub fn from_env() -> Self {
+		let default = Self::default();
+
+		Self {
+			max_reminders_per_user: env_or("MAX_REMINDERS_PER_USER", default.max_reminders_per_user),
+			max_reminders_per_guild: env_or("MAX_REMINDERS_PER_GUILD", default.max_reminders_per_guild),
+			max_message_len: env_or("MAX_MESSAGE_LEN", default.max_message_len),
+			max_future_secs: env_or("MAX_FUTURE_DAYS", default.max_future_secs / DAY_SECS) * DAY_SECS,
+			max_created_per_window: env_or("RATE_LIMIT_COUNT", default.max_created_per_window),
+			rate_window_secs: env_or("RATE_LIMIT_SECS", default.rate_window_secs),
+			..default
+		}
+	}
+
+	/// Checks the message and time of a reminder being made or edited.
+	pub fn check_content(&self, reminder: &Reminder, now: i64) -> Result<(), LimitError> {
+		let len = reminder.message.chars().count();
+		if len > self.max_message_len {
+			return Err(LimitError::MessageTooLong {
+				len,
+				max: self.max_message_len,
+			});
+		}
+
+		if reminder.timestamp - now > self.max_future_secs {
+			return Err(LimitError::TooFarAhead {
+				max_secs: self.max_future_secs,
+			});
+		}
+
+		Ok(())
+	}
+
+	/// Checks that a user can make these reminders all at once, all in the same place,
+	/// and counts them against the rate limit if so.
+	pub fn check_new(&self, db: &Db, reminders: &[Reminder], now: i64) -> Result<(), LimitError> {
+		let Some(first) = reminders.first() else {
+			return Ok(());
+		};
+
+		for reminder in reminders {
+			self.check_content(reminder, now)?;
+		}
+
+		if db.count_user_reminders(first.user_id).unwrap() + reminders.len() > self.max_reminders_per_user {
+			return Err(LimitError::TooManyReminders {
+				max: self.max_reminders_per_user,
+			});
+		}
+
+		if let Some(guild_id) = first.guild_id {
+			if db.count_guild_reminders(guild_id).unwrap() + reminders.len() > self.max_reminders_per_guild {
+				return Err(LimitError::TooManyInGuild {
+					max: self.max_reminders_per_guild,
+				});
+			}
+		}
+
+		let mut recently_created = self.recently_created.lock().unwrap();
+		recently_created.retain(|_, created| {
+			while created
+				.front()
+				.is_some_and(|&created| now - created >= self.rate_window_secs)
+			{
+				created.pop_front();
+			}
+			!created.is_empty()
+		});
+
+		let created = recently_created.entry(first.user_id).or_default();
+		if created.len() >= self.max_created_per_window {
+			return Err(LimitError::TooFast {
+				retry_at: created[0] + self.rate_window_secs,
+			});
+		}
+
+		created.push_back(now);
+		Ok(())
+	}
+}
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+	match env::var(name) {
+		Ok(value) => (value.parse().ok()).unwrap_or_else(|| panic!("{name} should be a positive number")),
+		Err(_) => default,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serenity::all::{ChannelId, GuildId};
+
+	use super::*;
+	use crate::delivery::Delivery;
+	use crate::mentions::Mentions;
+
+	fn reminder(user_id: u64, guild_id: u64, timestamp: i64, message: &str) -> Reminder {
+		Reminder {
+			id: 0,
+			timestamp,
+			user_id: UserId::new(user_id),
+			channel_id: ChannelId::new(1),
+			guild_id: Some(GuildId::new(guild_id)),
+			message: message.to_string(),
+			recurrence: None,
+			deliver: Delivery::Channel,
+			mentions: Mentions::default(),
+			subscribers: Vec::new(),
+			post: None,
+			retry: None,
+		}
+	}
+
+	#[test]
+	fn limits() {
+		let db = Db::open(":memory:").unwrap();
+		let limits = Limits {
+			max_reminders_per_user: 2,
+			max_reminders_per_guild: 3,
+			max_message_len: 10,
+			max_future_secs: DAY_SECS,
+			max_created_per_window: 2,
+			rate_window_secs: 60,
+			..Limits::default()
+		};
+
+		let check = |rem: &Reminder, now: i64| limits.check_new(&db, std::slice::from_ref(rem), now);
+
+		assert_eq!(
+			check(&reminder(1, 1, 200, "way too long"), 100),
+			Err(LimitError::MessageTooLong { len: 12, max: 10 })
+		);
+		assert_eq!(
+			check(&reminder(1, 1, 200 + DAY_SECS, "later"), 100),
+			Err(LimitError::TooFarAhead { max_secs: DAY_SECS })
+		);
+
+		// two per minute
+		for now in [100, 110] {
+			check(&reminder(1, 1, 200, "hi"), now).unwrap();
+			db.add_reminder(&reminder(1, 1, 200, "hi")).unwrap();
+		}
+		assert_eq!(check(&reminder(2, 1, 200, "hi"), 120), Ok(()));
+		db.add_reminder(&reminder(2, 1, 200, "hi")).unwrap();
+
+		// two each, and three in the server
+		assert_eq!(
+			check(&reminder(1, 2, 200, "hi"), 200),
+			Err(LimitError::TooManyReminders { max: 2 })
+		);
+		assert_eq!(
+			check(&reminder(3, 1, 200, "hi"), 200),
+			Err(LimitError::TooManyInGuild { max: 3 })
+		);
+
+		check(&reminder(3, 2, 200, "hi"), 200).unwrap();
+		check(&reminder(3, 2, 200, "hi"), 210).unwrap();
+		assert_eq!(
+			check(&reminder(3, 2, 200, "hi"), 220),
+			Err(LimitError::TooFast { retry_at: 260 })
+		);
+		assert_eq!(check(&reminder(3, 2, 200, "hi"), 260), Ok(()));
+	}
+}
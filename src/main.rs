@@ -1,11 +1,12 @@
-use std::collections::VecDeque;
 use std::env;
 use std::sync::{Arc, RwLock};
 
-use reminders::{load_reminders, Reminder};
+use db::{Db, DB_FILE_NAME};
+use limits::Limits;
+use reminders::SystemClock;
 use serenity::all::{
 	Command, CreateInteractionResponse, CreateInteractionResponseMessage, CurrentUser, EventHandler, GatewayIntents,
-	Interaction, Permissions, Ready,
+	Interaction, MessageUpdateEvent, Permissions, Ready,
 };
 use serenity::model::prelude::Message;
 use serenity::prelude::Context;
@@ -15,22 +16,42 @@ use tracing::level_filters::LevelFilter;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod commands;
+mod db;
+mod delivery;
+mod group;
+mod ical;
+mod legacy;
+mod limits;
+mod mentions;
+mod modlog;
 mod reminders;
+mod rules;
+mod snooze;
 mod soliloquy;
+mod strikes;
+mod time_expr;
+mod timezones;
 
 const PIN_MESSAGES_PERMISSION: Permissions = Permissions::from_bits_retain(1 << 51);
 
+/// How late reminders have to be to get grouped into a single summary per user, unless set in `SUMMARY_AFTER_SECS`.
+const DEFAULT_SUMMARY_AFTER_SECS: i64 = 15 * 60;
+
 #[derive(Clone)]
 pub struct AiChan {
 	bot: Arc<RwLock<Option<CurrentUser>>>,
-	reminders: Arc<RwLock<VecDeque<Reminder>>>,
+	db: Arc<Db>,
+	limits: Arc<Limits>,
+	summary_after_secs: i64,
 }
 
 impl AiChan {
-	pub fn new(reminders: VecDeque<Reminder>) -> Self {
+	pub fn new(db: Db, limits: Limits, summary_after_secs: i64) -> Self {
 		Self {
 			bot: Arc::new(RwLock::new(None)),
-			reminders: Arc::new(RwLock::new(reminders)),
+			db: Arc::new(db),
+			limits: Arc::new(limits),
+			summary_after_secs,
 		}
 	}
 }
@@ -41,6 +62,7 @@ impl EventHandler for AiChan {
 		let permissions = Permissions::SEND_MESSAGES
 			| Permissions::MANAGE_MESSAGES
 			| Permissions::VIEW_CHANNEL
+			| Permissions::MODERATE_MEMBERS
 			| PIN_MESSAGES_PERMISSION;
 		tracing::info!(
 			"Ready! Invite link: https://discord.com/api/oauth2/authorize?client_id={}&permissions={}&scope=bot",
@@ -53,9 +75,18 @@ impl EventHandler for AiChan {
 			&ctx.http,
 			vec![
 				commands::remindme::register(),
+				commands::editreminder::register(),
 				commands::myreminders::register(),
 				commands::selfmute::register(),
 				commands::threadpin::register(),
+				commands::remindmessage::register(),
+				commands::reminderdelivery::register(),
+				commands::timezone::register(),
+				commands::schedule::register(),
+				commands::soliloquy::register(),
+				commands::channelrules::register(),
+				commands::modlog::register(),
+				commands::strikes::register(),
 			],
 		)
 		.await
@@ -64,48 +95,138 @@ impl EventHandler for AiChan {
 			Err(e) => tracing::error!("Could not create global slash commands: {e}",),
 		};
 
-		let reminders = Arc::clone(&self.reminders);
+		let db = Arc::clone(&self.db);
+		let summary_after_secs = self.summary_after_secs;
 		tokio::spawn(async move {
-			reminders::process_reminders_every_second(reminders, ctx).await;
+			reminders::process_reminders(db, SystemClock, ctx, summary_after_secs).await;
 		});
 	}
 
 	async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-		if let Interaction::Command(command) = interaction {
-			tracing::info!(
-				"Received command interaction {:?} from {}",
-				&command.data.name,
-				&command.user.name
-			);
-
-			match command.data.name.as_str() {
-				commands::remindme::NAME => {
-					commands::remindme::run(Arc::clone(&self.reminders), &ctx, &command).await;
-				}
-				commands::myreminders::NAME => {
-					commands::myreminders::run(Arc::clone(&self.reminders), &ctx, &command).await;
+		match interaction {
+			Interaction::Command(command) => {
+				tracing::info!(
+					"Received command interaction {:?} from {}",
+					&command.data.name,
+					&command.user.name
+				);
+
+				// logged first, so turning the mod-log off gets logged too
+				if let Some(guild_id) = command.guild_id {
+					if commands::PRIVILEGED.contains(&command.data.name.as_str()) {
+						modlog::log(&self.db, &ctx, guild_id, modlog::command_embed(&command)).await;
+					}
 				}
-				commands::selfmute::NAME => {
-					commands::selfmute::run(&ctx, command).await;
+
+				match command.data.name.as_str() {
+					commands::remindme::NAME => {
+						commands::remindme::run(&self.db, &self.limits, &ctx, &command).await;
+					}
+					commands::myreminders::NAME => {
+						commands::myreminders::run(&self.db, &ctx, &command).await;
+					}
+					commands::editreminder::NAME => {
+						commands::editreminder::run(&self.db, &ctx, &command).await;
+					}
+					commands::selfmute::NAME => {
+						commands::selfmute::run(&ctx, command).await;
+					}
+					commands::threadpin::NAME => {
+						commands::threadpin::run(&ctx, command).await;
+					}
+					commands::remindmessage::NAME => {
+						commands::remindmessage::run(&ctx, &command).await;
+					}
+					commands::reminderdelivery::NAME => {
+						commands::reminderdelivery::run(&self.db, &ctx, &command).await;
+					}
+					commands::timezone::NAME => {
+						commands::timezone::run(&self.db, &ctx, &command).await;
+					}
+					commands::schedule::NAME => {
+						commands::schedule::run(&self.db, &self.limits, &ctx, &command).await;
+					}
+					commands::soliloquy::NAME => {
+						commands::soliloquy::run(&self.db, &ctx, &command).await;
+					}
+					commands::channelrules::NAME => {
+						commands::channelrules::run(&self.db, &ctx, &command).await;
+					}
+					commands::modlog::NAME => {
+						commands::modlog::run(&self.db, &ctx, &command).await;
+					}
+					commands::strikes::NAME => {
+						commands::strikes::run(&self.db, &ctx, &command).await;
+					}
+					name => {
+						let builder = CreateInteractionResponse::Message(
+							CreateInteractionResponseMessage::new()
+								.content(format!("Sorry, I don't have any `{name}` command :c")),
+						);
+						if let Err(e) = command.create_response(&ctx.http, builder).await {
+							tracing::error!("Cannot respond to slash command: {e}");
+						}
+					}
+				};
+			}
+			Interaction::Autocomplete(autocomplete) => match autocomplete.data.name.as_str() {
+				commands::myreminders::NAME | commands::editreminder::NAME => {
+					commands::autocomplete_reminder_id(&self.db, &ctx, &autocomplete).await;
 				}
-				commands::threadpin::NAME => {
-					commands::threadpin::run(&ctx, command).await;
+				name => tracing::error!("No autocomplete for command {name:?}"),
+			},
+			Interaction::Component(component) => {
+				tracing::info!(
+					"Received component interaction {:?} from {}",
+					&component.data.custom_id,
+					&component.user.name
+				);
+
+				let custom_id = component.data.custom_id.as_str();
+				if custom_id.starts_with(snooze::CUSTOM_ID_PREFIX) {
+					snooze::handle_component(&self.db, &self.limits, &ctx, &component).await;
+				} else if custom_id.starts_with(commands::remindme::IMPORT_ID_PREFIX) {
+					commands::remindme::handle_import_component(&self.db, &self.limits, &ctx, &component).await;
+				} else if custom_id.starts_with(group::CUSTOM_ID_PREFIX) {
+					group::handle_component(&self.db, &ctx, &component).await;
+				} else if custom_id.starts_with(commands::myreminders::CUSTOM_ID_PREFIX) {
+					commands::myreminders::handle_component(&self.db, &ctx, &component).await;
+				} else {
+					tracing::error!("Unknown component {custom_id:?}");
 				}
-				name => {
-					let builder = CreateInteractionResponse::Message(
-						CreateInteractionResponseMessage::new()
-							.content(format!("Sorry, I don't have any `{name}` command :c")),
-					);
-					if let Err(e) = command.create_response(&ctx.http, builder).await {
-						tracing::error!("Cannot respond to slash command: {e}");
-					}
+			}
+			Interaction::Modal(modal) => {
+				tracing::info!(
+					"Received modal interaction {:?} from {}",
+					&modal.data.custom_id,
+					&modal.user.name
+				);
+
+				let custom_id = modal.data.custom_id.as_str();
+				if custom_id.starts_with(commands::editreminder::MODAL_ID_PREFIX) {
+					commands::editreminder::handle_modal(&self.db, &self.limits, &ctx, &modal).await;
+				} else if custom_id.starts_with(commands::remindmessage::MODAL_ID_PREFIX) {
+					commands::remindmessage::handle_modal(&self.db, &self.limits, &ctx, &modal).await;
+				} else {
+					tracing::error!("Unknown modal {custom_id:?}");
 				}
-			};
+			}
+			_ => {}
 		}
 	}
 
 	async fn message(&self, ctx: Context, message: Message) {
-		soliloquy::handle_message(self.bot.as_ref(), ctx, message).await;
+		rules::handle_message(&self.db, self.bot.as_ref(), ctx, message).await;
+	}
+
+	async fn message_update(
+		&self,
+		ctx: Context,
+		_old: Option<Message>,
+		new: Option<Message>,
+		event: MessageUpdateEvent,
+	) {
+		rules::handle_message_update(&self.db, self.bot.as_ref(), ctx, new, event).await;
 	}
 }
 
@@ -121,11 +242,19 @@ async fn main() {
 	tracing::info!("AI-chan is booting up...");
 	let token = env::var("TOKEN").expect("No token provided in env var TOKEN");
 
-	tracing::info!("Loading reminders...");
-	let reminders = load_reminders().expect("Could not load reminders");
+	tracing::info!("Opening database...");
+	let db = Db::open(DB_FILE_NAME).expect("Could not open database");
+	if let Err(e) = legacy::import_legacy_reminders(&db) {
+		tracing::error!("Could not import the old reminders file: {e}");
+	}
+
+	let summary_after_secs = match env::var("SUMMARY_AFTER_SECS") {
+		Ok(secs) => secs.parse().expect("SUMMARY_AFTER_SECS should be a number of seconds"),
+		Err(_) => DEFAULT_SUMMARY_AFTER_SECS,
+	};
 
 	tracing::info!("Loading Discord bot client...");
-	let ai_chan = AiChan::new(reminders);
+	let ai_chan = AiChan::new(db, Limits::from_env(), summary_after_secs);
 
 	use GatewayIntents as G;
 	let mut client = Client::builder(&token, G::GUILD_MESSAGES | G::MESSAGE_CONTENT)
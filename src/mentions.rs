@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serenity::all::{ChannelId, Context, CreateAllowedMentions, Permissions, Role, RoleId, UserId};
+
+/// Who a reminder pings, besides the user who made it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Mentions {
+	pub users: Vec<UserId>,
+	pub roles: Vec<RoleId>,
+	/// Everyone online in the channel.
+	pub here: bool,
+}
+
+impl Mentions {
+	pub fn is_empty(&self) -> bool {
+		self.users.is_empty() && self.roles.is_empty() && !self.here
+	}
+
+	/// Parses mentions the way Discord writes them, like `<@123> <@&456> @here`.
+	///
+	/// On error, returns the first word that isn't a mention.
+	pub fn parse(text: &str) -> Result<Self, &str> {
+		let mut mentions = Mentions::default();
+
+		for word in text
+			.split(|c: char| c.is_whitespace() || c == ',')
+			.filter(|word| !word.is_empty())
+		{
+			if word == "@here" {
+				mentions.here = true;
+				continue;
+			}
+
+			let inner = (word.strip_prefix("<@"))
+				.and_then(|inner| inner.strip_suffix('>'))
+				.ok_or(word)?;
+
+			let parse_id = |id: &str| id.parse::<u64>().ok().filter(|&id| id != 0).ok_or(word);
+
+			if let Some(role) = inner.strip_prefix('&') {
+				let role = RoleId::new(parse_id(role)?);
+				if !mentions.roles.contains(&role) {
+					mentions.roles.push(role);
+				}
+			} else {
+				let user = UserId::new(parse_id(inner.strip_prefix('!').unwrap_or(inner))?);
+				if !mentions.users.contains(&user) {
+					mentions.users.push(user);
+				}
+			}
+		}
+
+		Ok(mentions)
+	}
+
+	/// The mentions that aren't in `other`.
+	pub fn without(&self, other: &Mentions) -> Mentions {
+		Mentions {
+			users: (self.users.iter())
+				.filter(|user| !other.users.contains(user))
+				.copied()
+				.collect(),
+			roles: (self.roles.iter())
+				.filter(|role| !other.roles.contains(role))
+				.copied()
+				.collect(),
+			here: self.here && !other.here,
+		}
+	}
+
+	/// Only lets these mentions and the author ping anyone, whatever else the message says.
+	pub fn allowed_mentions(&self, author: UserId) -> CreateAllowedMentions {
+		CreateAllowedMentions::new()
+			.users(std::iter::once(author).chain(self.users.iter().copied()))
+			.roles(self.roles.iter().copied())
+			.everyone(self.here)
+	}
+
+	/// The mentions someone with these permissions can make, following Discord's own rules:
+	/// anyone can ping users and mentionable roles, but `@here`, `@everyone` and the other roles
+	/// need the "Mention @everyone, @here, and All Roles" permission.
+	fn permitted(&self, permissions: Permissions, roles: &HashMap<RoleId, Role>) -> Mentions {
+		let mention_everyone = permissions.mention_everyone();
+
+		Mentions {
+			users: self.users.clone(),
+			roles: (self.roles.iter())
+				.filter(|role_id| {
+					roles.get(role_id).is_some_and(|role| {
+						// the @everyone role has the same ID as the server
+						let is_everyone = role.id.get() == role.guild_id.get();
+						mention_everyone || (role.mentionable && !is_everyone)
+					})
+				})
+				.copied()
+				.collect(),
+			here: self.here && mention_everyone,
+		}
+	}
+}
+
+impl fmt::Display for Mentions {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut words = Vec::new();
+		words.extend(self.users.iter().map(|user| format!("<@{user}>")));
+		words.extend(self.roles.iter().map(|role| format!("<@&{role}>")));
+		if self.here {
+			words.push("@here".to_string());
+		}
+
+		write!(f, "{}", words.join(" "))
+	}
+}
+
+/// The permissions of the user in a server channel, along with the server's roles.
+/// None outside of servers.
+pub async fn channel_permissions(
+	ctx: &Context,
+	user_id: UserId,
+	channel_id: ChannelId,
+) -> serenity::Result<Option<(Permissions, HashMap<RoleId, Role>)>> {
+	let Some(channel) = channel_id.to_channel(&ctx.http).await?.guild() else {
+		return Ok(None);
+	};
+
+	let guild = channel.guild_id.to_partial_guild(&ctx.http).await?;
+	let member = channel.guild_id.member(&ctx.http, user_id).await?;
+
+	Ok(Some((guild.user_permissions_in(&channel, &member), guild.roles)))
+}
+
+/// The mentions the user is currently allowed to make in the channel.
+///
+/// Outside of servers, or if their permissions can't be checked, only users can be pinged.
+pub async fn permitted_mentions(
+	ctx: &Context,
+	user_id: UserId,
+	channel_id: ChannelId,
+	mentions: &Mentions,
+) -> Mentions {
+	let users_only = Mentions {
+		users: mentions.users.clone(),
+		..Default::default()
+	};
+
+	if mentions.roles.is_empty() && !mentions.here {
+		return users_only;
+	}
+
+	match channel_permissions(ctx, user_id, channel_id).await {
+		Ok(Some((permissions, roles))) => mentions.permitted(permissions, &roles),
+		Ok(None) => users_only,
+		Err(e) => {
+			tracing::warn!("Cannot check mention permissions of {user_id} in channel {channel_id}: {e}");
+			users_only
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mentions_round_trip() {
+		let mentions = Mentions::parse("<@12> <@!34>, <@&56> @here <@12>").unwrap();
+		assert_eq!(mentions.users, [UserId::new(12), UserId::new(34)]);
+		assert_eq!(mentions.roles, [RoleId::new(56)]);
+		assert!(mentions.here);
+
+		assert_eq!(mentions.to_string(), "<@12> <@34> <@&56> @here");
+		assert_eq!(Mentions::parse(&mentions.to_string()), Ok(mentions));
+
+		assert_eq!(Mentions::parse(""), Ok(Mentions::default()));
+		assert_eq!(Mentions::parse("<@12> @everyone"), Err("@everyone"));
+		assert_eq!(Mentions::parse("<@&0>"), Err("<@&0>"));
+	}
+
+	#[test]
+	fn only_mentionable_roles_without_permission() {
+		let guild_id = 1;
+		let role = |id: u64, mentionable: bool| {
+			let mut role = Role::default();
+			role.id = RoleId::new(id);
+			role.guild_id = guild_id.into();
+			role.mentionable = mentionable;
+			(role.id, role)
+		};
+		let roles = HashMap::from([role(guild_id, false), role(2, true), role(3, false)]);
+
+		let mentions = Mentions::parse("<@5> <@&1> <@&2> <@&3> <@&4> @here").unwrap();
+
+		let permitted = mentions.permitted(Permissions::SEND_MESSAGES, &roles);
+		assert_eq!(permitted, Mentions::parse("<@5> <@&2>").unwrap());
+		assert_eq!(
+			mentions.without(&permitted),
+			Mentions::parse("<@&1> <@&3> <@&4> @here").unwrap()
+		);
+
+		let permitted = mentions.permitted(Permissions::MENTION_EVERYONE, &roles);
+		assert_eq!(permitted, Mentions::parse("<@5> <@&1> <@&2> <@&3> @here").unwrap());
+	}
+}
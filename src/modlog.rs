@@ -0,0 +1,147 @@
+use serenity::all::{
+	Colour, CommandDataOption, CommandDataOptionValue, CommandInteraction, Context, CreateAllowedMentions, CreateEmbed,
+	CreateEmbedFooter, CreateMessage, GuildId, Message, Timestamp,
+};
+
+use crate::db::Db;
+use crate::rules::{Enforcement, Notified};
+use crate::strikes::Strike;
+
+/// Discord doesn't take embed descriptions longer than that.
+const MAX_DESCRIPTION_LEN: usize = 4096;
+
+/// Nor field values longer than that.
+const MAX_FIELD_LEN: usize = 1024;
+
+const ENFORCEMENT_COLOUR: Colour = Colour::RED;
+const COMMAND_COLOUR: Colour = Colour::BLUE;
+const ALERT_COLOUR: Colour = Colour::ORANGE;
+
+/// Posts the embed in the server's mod-log channel, if it has one.
+pub async fn log(db: &Db, ctx: &Context, guild_id: GuildId, embed: CreateEmbed) {
+	let Some(channel_id) = db.mod_log_channel(guild_id).unwrap() else {
+		return;
+	};
+
+	// it's a log, nobody should get pinged by it
+	let message = CreateMessage::new()
+		.embed(embed)
+		.allowed_mentions(CreateAllowedMentions::new());
+	if let Err(e) = channel_id.send_message(&ctx.http, message).await {
+		tracing::error!("Could not post in the mod-log channel {channel_id} of {guild_id}: {e}");
+	}
+}
+
+/// What happened to a message breaking a rule.
+pub fn enforcement_embed(message: &Message, enforcement: &Enforcement) -> CreateEmbed {
+	let title = match (enforcement.deleted, enforcement.edited) {
+		(true, false) => "Deleted a message",
+		(true, true) => "Deleted an edited message",
+		(false, _) => "Could not delete a message",
+	};
+
+	let notified = match enforcement.notified {
+		Notified::Dm => "Sent them a DM",
+		Notified::Reply => "Couldn't DM them, replied in the channel",
+		Notified::Nobody => "Couldn't tell them :c",
+	};
+
+	let strikes = match &enforcement.strike {
+		Some(strike) => match (strike.timeout_until, enforcement.timed_out) {
+			(Some(until), true) => format!("{}, timed out until <t:{until}:t>", strike.count),
+			(Some(_), false) => format!("{}, but I couldn't time them out :c", strike.count),
+			(None, _) => strike.count.to_string(),
+		},
+		None if !enforcement.deleted => "None, as the message is still there".to_string(),
+		None => "None, they're a bot or a moderator".to_string(),
+	};
+
+	let content = match message.content.is_empty() {
+		true => "*(no text)*".to_string(),
+		false => truncate(&message.content, MAX_DESCRIPTION_LEN),
+	};
+
+	CreateEmbed::new()
+		.title(title)
+		.colour(ENFORCEMENT_COLOUR)
+		.description(content)
+		.field(
+			"Author",
+			format!("<@{}> ({})", message.author.id, message.author.name),
+			true,
+		)
+		.field("Channel", format!("<#{}>", message.channel_id), true)
+		.field("Rule", truncate(&enforcement.rule.name(), MAX_FIELD_LEN), true)
+		.field("Told them", notified, true)
+		.field("Strikes", strikes, true)
+		.footer(CreateEmbedFooter::new(format!("Message {}", message.id)))
+		.timestamp(Timestamp::now())
+}
+
+/// Someone keeps breaking the rules, and a moderator should have a look.
+pub fn repeat_offender_embed(message: &Message, strike: &Strike) -> CreateEmbed {
+	CreateEmbed::new()
+		.title("Someone keeps breaking the rules")
+		.colour(ALERT_COLOUR)
+		.description(format!(
+			"<@{}> ({}) has {} strikes now, could a moderator have a look? See `/strikes view` for what they did~",
+			message.author.id, message.author.name, strike.count
+		))
+		.timestamp(Timestamp::now())
+}
+
+/// Someone used a command needing permissions.
+pub fn command_embed(command: &CommandInteraction) -> CreateEmbed {
+	let mut options = Vec::new();
+	format_options(&command.data.options, "", &mut options);
+
+	let mut embed = CreateEmbed::new()
+		.title(format!("/{} used", command.data.name))
+		.colour(COMMAND_COLOUR)
+		.field("By", format!("<@{}> ({})", command.user.id, command.user.name), true)
+		.field("In", format!("<#{}>", command.channel_id), true)
+		.timestamp(Timestamp::now());
+
+	if !options.is_empty() {
+		embed = embed.description(truncate(&options.join("\n"), MAX_DESCRIPTION_LEN));
+	}
+
+	embed
+}
+
+/// Cuts the text so it fits, keeping its lines unlike [`crate::reminders::excerpt`].
+fn truncate(text: &str, max_chars: usize) -> String {
+	if text.chars().count() <= max_chars {
+		return text.to_string();
+	}
+
+	text.chars().take(max_chars - 3).collect::<String>() + "..."
+}
+
+/// One line per option, subcommands being prefixes of their own options.
+fn format_options(options: &[CommandDataOption], prefix: &str, lines: &mut Vec<String>) {
+	for option in options {
+		let value = match &option.value {
+			CommandDataOptionValue::SubCommand(options) | CommandDataOptionValue::SubCommandGroup(options) => {
+				let prefix = format!("{prefix}{} ", option.name);
+				if options.is_empty() {
+					lines.push(prefix.trim_end().to_string());
+				}
+				format_options(options, &prefix, lines);
+				continue;
+			}
+			CommandDataOptionValue::String(s) => format!("`{}`", s.replace('`', "ˋ")),
+			CommandDataOptionValue::Integer(n) => n.to_string(),
+			CommandDataOptionValue::Number(n) => n.to_string(),
+			CommandDataOptionValue::Boolean(b) => b.to_string(),
+			CommandDataOptionValue::Channel(id) => format!("<#{id}>"),
+			CommandDataOptionValue::User(id) => format!("<@{id}>"),
+			CommandDataOptionValue::Role(id) => format!("<@&{id}>"),
+			CommandDataOptionValue::Mentionable(id) => format!("`{id}`"),
+			CommandDataOptionValue::Attachment(id) => format!("attachment `{id}`"),
+			value => format!("{value:?}"),
+		};
+
+		lines.push(format!("{prefix}{}: {value}", option.name));
+	}
+}
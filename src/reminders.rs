@@ -1,17 +1,15 @@
-use std::{
-	collections::VecDeque,
-	fs::File,
-	io::{self, BufReader, BufWriter, Read, Write},
-	ops::Deref,
-	sync::{
-		atomic::{AtomicI64, Ordering},
-		Arc, RwLock,
-	},
-	time::SystemTime,
-};
-
-use chrono::{DateTime, TimeDelta, Timelike, Utc};
-use serenity::all::{ChannelId, Context, CreateMessage, UserId};
+use std::{collections::BTreeMap, fmt, future::Future, sync::Arc, time::Duration, time::SystemTime};
+
+use chrono::{DateTime, Datelike, NaiveTime, TimeDelta, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use futures::future::join_all;
+use serenity::all::{ChannelId, Context, GuildId, UserId};
+
+use crate::db::Db;
+use crate::delivery::{send_reminder, Delivery, SendResult};
+use crate::mentions::Mentions;
+use crate::snooze;
+use crate::timezones::{local_timestamp, user_timezone};
 
 pub fn date_time_now() -> chrono::DateTime<Utc> {
 	chrono::DateTime::<Utc>::from_timestamp_micros(
@@ -23,7 +21,101 @@ pub fn date_time_now() -> chrono::DateTime<Utc> {
 	.unwrap()
 }
 
-pub static NEXT_REMINDER_ID: AtomicI64 = AtomicI64::new(0);
+/// How a reminder comes back after it has been sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recurrence {
+	/// Every fixed amount of seconds.
+	Interval(i64),
+	/// Every week on the same day, at the same time of day in the user's time zone.
+	Weekly(Weekday, NaiveTime),
+}
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Longest interval a reminder can repeat at. Nobody needs one coming back less than once a century,
+/// and it keeps the next occurrences well within what dates can hold.
+pub const MAX_REPEAT_SECS: i64 = 100 * 365 * DAY_SECS;
+
+impl Recurrence {
+	/// Timestamp of the first occurrence strictly after `now`, starting from the occurrence at `timestamp`.
+	///
+	/// None if it would be further than dates go, in which case the reminder stops repeating.
+	pub fn next_after(&self, timestamp: i64, now: i64, tz: Tz) -> Option<i64> {
+		let skipped = |secs: i64| (now - timestamp).max(0) / secs;
+
+		match *self {
+			Recurrence::Interval(secs) if secs % DAY_SECS == 0 => {
+				// whole days keep the same wall-clock time when DST starts or ends
+				let days = secs / DAY_SECS;
+				let start = tz.from_utc_datetime(&DateTime::from_timestamp(timestamp, 0)?.naive_utc());
+
+				let skipped_days = TimeDelta::try_days(days.checked_mul(skipped(secs))?)?;
+				let mut next = start.naive_local().checked_add_signed(skipped_days)?;
+				loop {
+					next = next.checked_add_signed(TimeDelta::try_days(days)?)?;
+
+					let next_timestamp = local_timestamp(tz, next);
+					if next_timestamp > now {
+						return Some(next_timestamp);
+					}
+				}
+			}
+			Recurrence::Interval(secs) => timestamp.checked_add((skipped(secs) + 1).checked_mul(secs)?),
+			Recurrence::Weekly(weekday, time) => {
+				let after = timestamp.max(now);
+				let after_date_time = DateTime::from_timestamp(after, 0)?;
+
+				let mut date = tz.from_utc_datetime(&after_date_time.naive_utc()).date_naive();
+				loop {
+					if date.weekday() == weekday {
+						let next_timestamp = local_timestamp(tz, date.and_time(time));
+						if next_timestamp > after {
+							return Some(next_timestamp);
+						}
+					}
+
+					date = date.succ_opt()?;
+				}
+			}
+		}
+	}
+}
+
+impl fmt::Display for Recurrence {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			Recurrence::Interval(secs) => {
+				let units = [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)];
+
+				let mut rest = secs;
+				let mut parts = Vec::new();
+				for (suffix, unit) in units {
+					if rest >= unit {
+						parts.push(format!("{}{suffix}", rest / unit));
+						rest %= unit;
+					}
+				}
+
+				write!(f, "every {}", parts.join(" "))
+			}
+			Recurrence::Weekly(weekday, time) => {
+				write!(f, "every {} at {}", weekday_name(weekday), time.format("%H:%M"))
+			}
+		}
+	}
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+	match weekday {
+		Weekday::Mon => "Monday",
+		Weekday::Tue => "Tuesday",
+		Weekday::Wed => "Wednesday",
+		Weekday::Thu => "Thursday",
+		Weekday::Fri => "Friday",
+		Weekday::Sat => "Saturday",
+		Weekday::Sun => "Sunday",
+	}
+}
 
 #[derive(Clone)]
 pub struct Reminder {
@@ -31,136 +123,668 @@ pub struct Reminder {
 	pub timestamp: i64,
 	pub user_id: UserId,
 	pub channel_id: ChannelId,
+	/// Server the reminder was made in, None in DMs.
+	pub guild_id: Option<GuildId>,
 	pub message: String,
+	pub recurrence: Option<Recurrence>,
+	pub deliver: Delivery,
+	/// Who else gets pinged.
+	pub mentions: Mentions,
+	/// Users who joined the reminder with the "Remind me too" button, also pinged.
+	pub subscribers: Vec<UserId>,
+	/// Set for messages scheduled with `/schedule`, posted as they are instead of as a reminder.
+	pub post: Option<Post>,
+	/// Set when sending the reminder failed and it's waiting to be tried again.
+	pub retry: Option<Retry>,
+}
+
+/// A reminder that failed to send, waiting in the retry queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Retry {
+	/// How many times sending it failed so far.
+	pub attempts: u32,
+	/// When it was originally due.
+	pub due: i64,
+}
+
+/// How a scheduled message looks, besides its content.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Post {
+	pub embed_title: Option<String>,
+	pub embed_description: Option<String>,
+	/// Publish it to the servers following the channel, if it's an announcement channel.
+	pub announce: bool,
+}
+
+/// How many times a reminder is tried before giving up on it.
+pub const MAX_SEND_ATTEMPTS: u32 = 6;
+
+/// How long to wait before trying again the first time. It doubles after every failure.
+const RETRY_BACKOFF_SECS: i64 = 30;
+
+/// What becomes of a reminder once the scheduler tried to send it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AfterSending {
+	/// It's done for good.
+	Delete,
+	/// It repeats, and comes back at this timestamp.
+	Reschedule(i64),
+	/// It couldn't be sent, and will be tried again at this timestamp.
+	Retry(i64, Retry),
+}
+
+/// Longest the scheduler sleeps in one go, so it catches up with the system clock if it jumped.
+const MAX_SLEEP_SECS: i64 = 60 * 60;
+
+/// Source of the current time for the scheduler, so it can be driven by hand in tests.
+pub trait Clock: Send + Sync {
+	/// Current Unix timestamp in seconds.
+	fn now(&self) -> i64;
+
+	/// Sleeps until the given Unix timestamp.
+	fn sleep_until(&self, timestamp: i64) -> impl Future<Output = ()> + Send;
 }
 
-impl Reminder {
-	fn write(&self, w: &mut impl Write) -> io::Result<()> {
-		w.write_all(&self.id.to_le_bytes())?;
-		w.write_all(&self.timestamp.to_le_bytes())?;
-		w.write_all(&self.user_id.get().to_le_bytes())?;
-		w.write_all(&self.channel_id.get().to_le_bytes())?;
-		w.write_all(&(self.message.len() as u64).to_le_bytes())?;
-		w.write_all(self.message.as_bytes())
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> i64 {
+		date_time_now().timestamp()
 	}
 
-	fn read(r: &mut impl Read) -> io::Result<Self> {
-		let mut id_bytes = [0_u8; 8];
-		r.read_exact(&mut id_bytes)?;
-		let id = i64::from_le_bytes(id_bytes);
+	fn sleep_until(&self, timestamp: i64) -> impl Future<Output = ()> + Send {
+		let remaining_micros = timestamp * 1_000_000 - date_time_now().timestamp_micros();
+		tokio::time::sleep(Duration::from_micros(remaining_micros.max(0) as u64))
+	}
+}
 
-		let mut timestamp_bytes = [0_u8; 8];
-		r.read_exact(&mut timestamp_bytes)?;
-		let timestamp = i64::from_le_bytes(timestamp_bytes);
+/// What the scheduler sends reminders with, so it can run without Discord in tests.
+pub trait ReminderSender: Send + Sync {
+	/// Sends a reminder with the given content, with snooze buttons if it has somewhere to snooze from.
+	fn send(
+		&self,
+		reminder: &Reminder,
+		content: String,
+		snoozable_id: Option<i64>,
+	) -> impl Future<Output = SendResult> + Send;
+}
 
-		let mut user_id_bytes = [0_u8; 8];
-		r.read_exact(&mut user_id_bytes)?;
-		let user_id = UserId::new(u64::from_le_bytes(user_id_bytes));
+impl ReminderSender for Context {
+	async fn send(&self, reminder: &Reminder, content: String, snoozable_id: Option<i64>) -> SendResult {
+		send_reminder(self, reminder, &content, snoozable_id).await
+	}
+}
+
+/// How late a reminder can be sent before it gets a "sorry I was late" note.
+const LATE_GRACE_SECS: i64 = 60;
 
-		let mut channel_id_bytes = [0_u8; 8];
-		r.read_exact(&mut channel_id_bytes)?;
-		let channel_id = ChannelId::new(u64::from_le_bytes(channel_id_bytes));
+/// How many reminders a catch-up summary lists at most.
+const SUMMARY_MAX_REMINDERS: usize = 20;
 
-		let mut message_len_bytes = [0_u8; 8];
-		r.read_exact(&mut message_len_bytes)?;
-		let message_len = u64::from_le_bytes(message_len_bytes) as usize;
+/// Sends reminders as they come due.
+///
+/// Sleeps until the next reminder is due, or until the reminders in the database change.
+/// Users with several reminders more than `summary_after_secs` late, like after AI-chan was down for a while,
+/// get them all in one summary instead.
+pub async fn process_reminders(db: Arc<Db>, clock: impl Clock, sender: impl ReminderSender, summary_after_secs: i64) {
+	loop {
+		let now = clock.now();
 
-		let mut message_bytes = vec![0_u8; message_len];
-		r.read_exact(message_bytes.as_mut_slice())?;
-		let message = String::from_utf8(message_bytes).unwrap();
+		send_due_reminders(&db, now, summary_after_secs, &sender).await;
 
-		Ok(Self {
-			id,
-			timestamp,
-			user_id,
-			channel_id,
-			message,
-		})
+		let next_timestamp = db.next_timestamp().unwrap();
+		let wake_up = next_timestamp.map_or(now + MAX_SLEEP_SECS, |ts| ts.min(now + MAX_SLEEP_SECS));
+
+		tokio::select! {
+			_ = clock.sleep_until(wake_up) => {}
+			_ = db.reminders_changed() => {}
+		}
 	}
 }
 
-const REMINDERS_FILE_NAME: &str = "ai-chan-reminders.bin";
+/// When a reminder was supposed to go out. Retries don't shift it.
+fn due_timestamp(reminder: &Reminder) -> i64 {
+	reminder.retry.map_or(reminder.timestamp, |retry| retry.due)
+}
 
-pub fn load_reminders() -> io::Result<VecDeque<Reminder>> {
-	let mut reminders = VecDeque::new();
+/// The start of a text on a single line, cut on a character boundary.
+pub fn excerpt(text: &str, max_chars: usize) -> String {
+	let text = text.replace(['\n', '\t'], " ");
+	if text.chars().count() <= max_chars {
+		return text;
+	}
 
-	let Ok(file) = File::open(REMINDERS_FILE_NAME) else {
-		return Ok(reminders);
-	};
+	text.chars().take(max_chars - 3).collect::<String>() + "..."
+}
 
-	let mut r = BufReader::new(file);
+fn reminder_content(reminder: &Reminder, next_timestamp: Option<i64>, now: i64) -> String {
+	if reminder.post.is_some() {
+		// scheduled messages go out as they were written, late or not
+		return match reminder.mentions.is_empty() {
+			true => reminder.message.clone(),
+			false => format!("{} {}", reminder.mentions, reminder.message),
+		};
+	}
 
-	let mut reminders_len_bytes = [0_u8; 8];
-	r.read_exact(&mut reminders_len_bytes)?;
-	let reminders_len = u64::from_le_bytes(reminders_len_bytes);
+	let mut content = format!("<@{}> ", reminder.user_id.get());
+	for subscriber in &reminder.subscribers {
+		content += &format!("<@{subscriber}> ");
+	}
+	if !reminder.mentions.is_empty() {
+		content += &format!("{} ", reminder.mentions);
+	}
+	content += &format!("Here's your reminder~\n\n{}", reminder.message);
 
-	for _ in 0..reminders_len {
-		reminders.push_back(Reminder::read(&mut r)?);
+	let mut footer = Vec::new();
+
+	if let (Some(recurrence), Some(next_timestamp)) = (reminder.recurrence, next_timestamp) {
+		footer.push(format!("Next one <t:{next_timestamp}:R> ({recurrence})"));
 	}
 
-	let next_reminder_id = reminders.iter().map(|rem| rem.id).max().unwrap_or_default() + 1;
-	NEXT_REMINDER_ID.store(next_reminder_id, Ordering::Relaxed);
+	let due = due_timestamp(reminder);
+	if now - due > LATE_GRACE_SECS {
+		footer.push(format!("This was due <t:{due}:R>, sorry I was late~"));
+	}
 
-	Ok(reminders)
+	if !footer.is_empty() {
+		content += "\n\n-# ";
+		content += &footer.join("\n-# ");
+	}
+
+	content
 }
 
-pub fn store_reminders(reminders: &VecDeque<Reminder>) -> io::Result<()> {
-	let mut w = BufWriter::new(File::create(REMINDERS_FILE_NAME)?);
+fn summary_content(reminders: &[&Reminder], next_timestamps: &[Option<i64>]) -> String {
+	let mut content = format!(
+		"<@{}> Sorry, I was away for a while and missed {} of your reminders! Here they are~\n",
+		reminders[0].user_id.get(),
+		reminders.len()
+	);
+
+	for (reminder, next_timestamp) in reminders.iter().zip(next_timestamps).take(SUMMARY_MAX_REMINDERS) {
+		// replace backticks with grave accent to avoid breaking the display
+		let message = excerpt(&reminder.message, 80).replace('`', "ˋ");
+		content += &format!("\n- <t:{}:f> `{message}`", due_timestamp(reminder));
 
-	w.write_all(&(reminders.len() as u64).to_le_bytes())?;
-	for reminder in reminders {
-		reminder.write(&mut w)?;
+		if let Some(next_timestamp) = next_timestamp {
+			content += &format!(" (next one <t:{next_timestamp}:R>)");
+		}
+	}
+
+	if reminders.len() > SUMMARY_MAX_REMINDERS {
+		content += &format!("\n...and {} more", reminders.len() - SUMMARY_MAX_REMINDERS);
 	}
 
-	Ok(())
+	content
 }
 
-pub async fn process_reminders_every_second(reminders: Arc<RwLock<VecDeque<Reminder>>>, ctx: Context) {
-	loop {
-		let now = date_time_now().with_nanosecond(0).unwrap();
+/// Sends all reminders due at `now` at the same time, then saves what happened to them all at once.
+async fn send_due_reminders(db: &Db, now: i64, summary_after_secs: i64, sender: &impl ReminderSender) {
+	let due_reminders = db.due_reminders(now).unwrap();
+	if due_reminders.is_empty() {
+		return;
+	}
 
-		loop {
-			let first_timestamp = reminders.read().unwrap().front().map(|r| r.timestamp);
-			if let Some(first_timestamp) = first_timestamp {
-				let date_time = DateTime::<Utc>::from_timestamp(first_timestamp, 0).unwrap();
+	// each batch is sent as one message
+	let mut batches = Vec::new();
+	let mut very_late = BTreeMap::<UserId, Vec<&Reminder>>::new();
+
+	for reminder in &due_reminders {
+		// scheduled messages and reminders pinging others get sent on their own, so they still get their ping
+		let own_message = reminder.post.is_some() || !reminder.mentions.is_empty() || !reminder.subscribers.is_empty();
+		if now - due_timestamp(reminder) > summary_after_secs && !own_message {
+			very_late.entry(reminder.user_id).or_default().push(reminder);
+		} else {
+			batches.push(vec![reminder]);
+		}
+	}
 
-				if date_time <= now {
-					let reminder = reminders.write().unwrap().pop_front().unwrap();
+	batches.extend(very_late.into_values());
+
+	let deliveries = batches.iter().map(|batch| async move {
+		let tz = user_timezone(db, batch[0].user_id);
+		let next_timestamps = (batch.iter())
+			.map(|reminder| {
+				(reminder.recurrence).and_then(|recurrence| recurrence.next_after(due_timestamp(reminder), now, tz))
+			})
+			.collect::<Vec<_>>();
+
+		// summaries can't be snoozed, nor can scheduled messages
+		let (content, snoozable_id) = match batch.as_slice() {
+			[reminder] if reminder.post.is_none() => {
+				let since = now - snooze::SNOOZABLE_FOR_SECS;
+				let snoozable_id = db
+					.add_snoozable(reminder.user_id, &reminder.message, now, since)
+					.unwrap();
+				(reminder_content(reminder, next_timestamps[0], now), Some(snoozable_id))
+			}
+			[reminder] => (reminder_content(reminder, next_timestamps[0], now), None),
+			batch => (summary_content(batch, &next_timestamps), None),
+		};
+
+		// summaries go where the first of their reminders would have
+		let result = sender.send(batch[0], content, snoozable_id).await;
+
+		(batch.iter().zip(next_timestamps))
+			.map(|(reminder, next_timestamp)| {
+				(
+					reminder.id,
+					reminder.timestamp,
+					after_sending(reminder, result, next_timestamp, now),
+				)
+			})
+			.collect::<Vec<_>>()
+	});
+
+	let sent = join_all(deliveries).await.concat();
+	db.finish_reminders(&sent).unwrap();
+}
 
-					let content = format!(
-						"<@{}> Here's your reminder~\n\n{}",
-						reminder.user_id.get(),
-						reminder.message
-					);
+fn after_sending(reminder: &Reminder, result: SendResult, next_timestamp: Option<i64>, now: i64) -> AfterSending {
+	let attempts = reminder.retry.map_or(0, |retry| retry.attempts) + 1;
 
-					let result = (reminder.channel_id)
-						.send_message(&ctx.http, CreateMessage::new().content(content))
-						.await;
+	match result {
+		SendResult::Sent => {}
+		SendResult::Failed if attempts < MAX_SEND_ATTEMPTS => {
+			let backoff = RETRY_BACKOFF_SECS << (attempts - 1);
+			tracing::warn!(
+				"Cannot send reminder {} right now, trying again in {backoff}s (attempt {attempts} of {MAX_SEND_ATTEMPTS})",
+				reminder.id
+			);
 
-					if let Err(e) = result {
-						tracing::error!("Cannot send reminder message: {e}");
-					}
+			let due = due_timestamp(reminder);
+			return AfterSending::Retry(now + backoff, Retry { attempts, due });
+		}
+		SendResult::Failed => tracing::error!("Giving up on reminder {} after {attempts} attempts", reminder.id),
+		SendResult::Undeliverable => tracing::error!("Cannot send reminder {} anywhere, giving up", reminder.id),
+	}
 
-					store_reminders(reminders.read().unwrap().deref()).unwrap();
-				} else {
-					break;
-				}
+	match next_timestamp {
+		Some(next_timestamp) => AfterSending::Reschedule(next_timestamp),
+		None => AfterSending::Delete,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex};
+
+	use serenity::all::{ChannelId, UserId};
+	use tokio::sync::watch;
+
+	use super::{
+		excerpt, process_reminders, AfterSending, Clock, Post, Recurrence, Reminder, ReminderSender, Retry,
+		MAX_SEND_ATTEMPTS,
+	};
+	use crate::db::Db;
+	use crate::delivery::{Delivery, SendResult};
+	use crate::mentions::Mentions;
+
+	/// Clock that only moves when the test says so.
+	#[derive(Clone)]
+	struct MockClock(Arc<watch::Sender<i64>>);
+
+	impl MockClock {
+		fn advance_to(&self, timestamp: i64) {
+			self.0.send_replace(timestamp);
+		}
+	}
+
+	impl Clock for MockClock {
+		fn now(&self) -> i64 {
+			*self.0.borrow()
+		}
+
+		async fn sleep_until(&self, timestamp: i64) {
+			let mut now = self.0.subscribe();
+			now.wait_for(|&now| now >= timestamp).await.unwrap();
+		}
+	}
+
+	/// Remembers what was sent and when, according to the mock clock.
+	/// Messages starting with "flaky" fail, and ones starting with "gone" can never be sent.
+	#[derive(Clone)]
+	struct MockSender {
+		clock: MockClock,
+		sent: Arc<Mutex<Vec<(i64, i64, String)>>>,
+		/// Snooze buttons each of them was sent with.
+		snoozable_ids: Arc<Mutex<Vec<Option<i64>>>>,
+	}
+
+	impl ReminderSender for MockSender {
+		async fn send(&self, reminder: &Reminder, content: String, snoozable_id: Option<i64>) -> SendResult {
+			let now = self.clock.now();
+			self.sent.lock().unwrap().push((reminder.id, now, content));
+			self.snoozable_ids.lock().unwrap().push(snoozable_id);
+
+			if reminder.message.starts_with("flaky") {
+				SendResult::Failed
+			} else if reminder.message.starts_with("gone") {
+				SendResult::Undeliverable
 			} else {
-				break;
+				SendResult::Sent
 			}
 		}
+	}
 
-		sleep_until_next_second().await;
+	const DAY: i64 = 24 * 60 * 60;
+
+	fn reminder(timestamp: i64, message: &str, recurrence: Option<Recurrence>) -> Reminder {
+		Reminder {
+			id: 0,
+			timestamp,
+			user_id: UserId::new(1),
+			channel_id: ChannelId::new(2),
+			guild_id: None,
+			message: message.to_string(),
+			recurrence,
+			deliver: Delivery::Channel,
+			mentions: Mentions::default(),
+			subscribers: Vec::new(),
+			post: None,
+			retry: None,
+		}
 	}
-}
 
-async fn sleep_until_next_second() {
-	let now = date_time_now();
+	/// Lets the scheduler run until it has sent `count` reminders in total.
+	async fn wait_for_sent(sender: &MockSender, count: usize) {
+		for _ in 0..1000 {
+			if sender.sent.lock().unwrap().len() >= count {
+				return;
+			}
+			tokio::task::yield_now().await;
+		}
+
+		panic!("expected {count} sent reminders, got {:?}", sender.sent.lock().unwrap());
+	}
+
+	#[tokio::test]
+	async fn scheduler() {
+		let db = Arc::new(Db::open(":memory:").unwrap());
+		let once = db.add_reminder(&reminder(100, "once", None)).unwrap();
+		let every = (db.add_reminder(&reminder(100, "every minute", Some(Recurrence::Interval(60))))).unwrap();
+
+		let clock = MockClock(Arc::new(watch::Sender::new(50)));
+		let sender = MockSender {
+			clock: clock.clone(),
+			sent: Arc::default(),
+			snoozable_ids: Arc::default(),
+		};
+
+		let scheduler = tokio::spawn(process_reminders(Arc::clone(&db), clock.clone(), sender.clone(), 3600));
+
+		// nothing is due yet
+		for _ in 0..10 {
+			tokio::task::yield_now().await;
+		}
+		assert!(sender.sent.lock().unwrap().is_empty());
+
+		// both reminders go out together, and only the repeating one stays
+		clock.advance_to(100);
+		wait_for_sent(&sender, 2).await;
+		{
+			let sent = sender.sent.lock().unwrap();
+			assert_eq!((sent[0].0, sent[0].1), (once, 100));
+			assert_eq!(sent[0].2, "<@1> Here's your reminder~\n\nonce");
+			assert_eq!((sent[1].0, sent[1].1), (every, 100));
+			assert!(sent[1].2.ends_with("-# Next one <t:160:R> (every 1m)"));
+		}
+
+		let remaining = db.user_reminders(UserId::new(1)).unwrap();
+		assert_eq!(remaining.len(), 1);
+		assert_eq!((remaining[0].id, remaining[0].timestamp), (every, 160));
+
+		// a reminder added while the scheduler sleeps until 160 wakes it up early
+		let added = db.add_reminder(&reminder(130, "added", None)).unwrap();
+		clock.advance_to(130);
+		wait_for_sent(&sender, 3).await;
+		assert_eq!(sender.sent.lock().unwrap()[2].0, added);
+		assert_eq!(sender.sent.lock().unwrap()[2].1, 130);
+
+		clock.advance_to(160);
+		wait_for_sent(&sender, 4).await;
+		assert_eq!(sender.sent.lock().unwrap()[3].0, every);
+		assert_eq!(db.next_timestamp().unwrap(), Some(220));
+
+		scheduler.abort();
+	}
+
+	#[tokio::test]
+	async fn group_reminders_ping_everyone_who_joined() {
+		let db = Arc::new(Db::open(":memory:").unwrap());
+		let group = db.add_reminder(&reminder(100, "standup", None)).unwrap();
+
+		// joining your own reminder does nothing
+		assert!(!db.subscribe(group, UserId::new(1), 2).unwrap());
+		assert!(db.subscribe(group, UserId::new(3), 2).unwrap());
+		assert!(db.subscribe(group, UserId::new(4), 2).unwrap());
+
+		// it's full with two people
+		assert!(!db.subscribe(group, UserId::new(5), 2).unwrap());
+		assert_eq!(db.count_subscribers(group).unwrap(), 2);
+
+		assert!(db.unsubscribe(group, UserId::new(4)).unwrap());
+		assert_eq!(db.user_reminders(UserId::new(3)).unwrap()[0].id, group);
+		assert!(db.user_reminders(UserId::new(4)).unwrap().is_empty());
 
-	let prev_time = now.time().with_nanosecond(0).unwrap();
-	let next_time = prev_time + TimeDelta::seconds(1);
-	let next_time = now.with_time(next_time).unwrap().to_utc();
+		let clock = MockClock(Arc::new(watch::Sender::new(100)));
+		let sender = MockSender {
+			clock: clock.clone(),
+			sent: Arc::default(),
+			snoozable_ids: Arc::default(),
+		};
+
+		let scheduler = tokio::spawn(process_reminders(Arc::clone(&db), clock.clone(), sender.clone(), 3600));
+
+		wait_for_sent(&sender, 1).await;
+		assert_eq!(
+			sender.sent.lock().unwrap()[0].2,
+			"<@1> <@3> Here's your reminder~\n\nstandup"
+		);
+
+		// subscriptions go away with the reminder
+		assert!(db.user_reminders(UserId::new(3)).unwrap().is_empty());
+
+		scheduler.abort();
+	}
+
+	#[tokio::test]
+	async fn scheduled_posts_go_out_as_written() {
+		let db = Arc::new(Db::open(":memory:").unwrap());
+		let post = Reminder {
+			post: Some(Post::default()),
+			..reminder(100, "Server maintenance tonight!", None)
+		};
+		let post = db.add_reminder(&post).unwrap();
+		db.add_reminder(&reminder(100, "late", None)).unwrap();
+		db.add_reminder(&reminder(100, "very late", None)).unwrap();
+
+		// way late, but posts don't get caught in the summary or apologize
+		let clock = MockClock(Arc::new(watch::Sender::new(100 + DAY)));
+		let sender = MockSender {
+			clock: clock.clone(),
+			sent: Arc::default(),
+			snoozable_ids: Arc::default(),
+		};
+
+		let scheduler = tokio::spawn(process_reminders(Arc::clone(&db), clock.clone(), sender.clone(), 3600));
+
+		wait_for_sent(&sender, 2).await;
+		{
+			let sent = sender.sent.lock().unwrap();
+			assert_eq!(sent.len(), 2);
+			let posted = sent.iter().find(|(id, _, _)| *id == post).unwrap();
+			assert_eq!(posted.2, "Server maintenance tonight!");
+		}
+
+		scheduler.abort();
+	}
 
-	let remaining = next_time.signed_duration_since(now).to_std().unwrap_or_default();
-	tokio::time::sleep(remaining).await;
+	#[tokio::test]
+	async fn failed_reminders_are_retried() {
+		let db = Arc::new(Db::open(":memory:").unwrap());
+		let flaky = db.add_reminder(&reminder(100, "flaky", None)).unwrap();
+		let gone = db.add_reminder(&reminder(100, "gone", None)).unwrap();
+
+		let clock = MockClock(Arc::new(watch::Sender::new(100)));
+		let sender = MockSender {
+			clock: clock.clone(),
+			sent: Arc::default(),
+			snoozable_ids: Arc::default(),
+		};
+
+		let scheduler = tokio::spawn(process_reminders(Arc::clone(&db), clock.clone(), sender.clone(), 3600));
+
+		wait_for_sent(&sender, 2).await;
+
+		// undeliverable reminders are dropped right away, failed ones wait in the retry queue
+		let remaining = db.user_reminders(UserId::new(1)).unwrap();
+		assert_eq!(remaining.len(), 1);
+		assert_eq!(remaining[0].id, flaky);
+		assert_eq!(remaining[0].timestamp, 130);
+		assert_eq!(remaining[0].retry, Some(Retry { attempts: 1, due: 100 }));
+		assert!(db.user_reminder(UserId::new(1), gone).unwrap().is_none());
+
+		// the wait doubles every time, until the last attempt
+		let mut timestamp = 130;
+		for attempts in 2..=MAX_SEND_ATTEMPTS {
+			clock.advance_to(timestamp);
+			wait_for_sent(&sender, attempts as usize + 1).await;
+			timestamp += 30 << (attempts - 1);
+
+			let retry = db
+				.user_reminder(UserId::new(1), flaky)
+				.unwrap()
+				.and_then(|rem| rem.retry);
+			if attempts < MAX_SEND_ATTEMPTS {
+				assert_eq!(retry, Some(Retry { attempts, due: 100 }));
+			} else {
+				assert_eq!(retry, None);
+			}
+		}
+
+		assert_eq!(db.next_timestamp().unwrap(), None);
+
+		scheduler.abort();
+	}
+
+	#[tokio::test]
+	async fn late_reminders_are_caught_up() {
+		let db = Arc::new(Db::open(":memory:").unwrap());
+		let first = db.add_reminder(&reminder(100, "first", None)).unwrap();
+		let second = (db.add_reminder(&reminder(200, "second\nline", Some(Recurrence::Interval(DAY))))).unwrap();
+		let other = db
+			.add_reminder(&Reminder {
+				user_id: UserId::new(3),
+				..reminder(100, "other user", None)
+			})
+			.unwrap();
+		let slightly_late = db
+			.add_reminder(&reminder(9_000, "slightly late\n\n-# not a footer", None))
+			.unwrap();
+		let on_time = db.add_reminder(&reminder(9_990, "on time", None)).unwrap();
+
+		// back online after a long time
+		let clock = MockClock(Arc::new(watch::Sender::new(10_000)));
+		let sender = MockSender {
+			clock: clock.clone(),
+			sent: Arc::default(),
+			snoozable_ids: Arc::default(),
+		};
+
+		let scheduler = tokio::spawn(process_reminders(Arc::clone(&db), clock.clone(), sender.clone(), 3600));
+
+		wait_for_sent(&sender, 4).await;
+		let sent = sender.sent.lock().unwrap().clone();
+		scheduler.abort();
+
+		let content = |id: i64| {
+			(sent.iter())
+				.find(|(sent_id, _, _)| *sent_id == id)
+				.map(|(_, _, content)| content.as_str())
+		};
+
+		assert_eq!(
+			content(first),
+			Some(
+				"<@1> Sorry, I was away for a while and missed 2 of your reminders! Here they are~\n\
+				\n- <t:100:f> `first`\
+				\n- <t:200:f> `second line` (next one <t:86600:R>)"
+			)
+		);
+		assert_eq!(content(second), None);
+		assert_eq!(
+			content(other),
+			Some("<@3> Here's your reminder~\n\nother user\n\n-# This was due <t:100:R>, sorry I was late~")
+		);
+		assert_eq!(
+			content(slightly_late),
+			Some(
+				"<@1> Here's your reminder~\n\nslightly late\n\n-# not a footer\n\n-# This was due <t:9000:R>, \
+				sorry I was late~"
+			)
+		);
+		assert_eq!(content(on_time), Some("<@1> Here's your reminder~\n\non time"));
+
+		// summaries can't be snoozed, and single reminders are snoozed with their whole text
+		let snoozable_ids = sender.snoozable_ids.lock().unwrap().clone();
+		let snoozable = |id: i64| {
+			let index = sent.iter().position(|(sent_id, _, _)| *sent_id == id).unwrap();
+			snoozable_ids[index].map(|snoozable_id| db.snoozable(snoozable_id, UserId::new(1)).unwrap())
+		};
+		assert_eq!(snoozable(first), None);
+		assert_eq!(
+			snoozable(slightly_late),
+			Some(Some("slightly late\n\n-# not a footer".to_string()))
+		);
+
+		assert_eq!(db.next_timestamp().unwrap(), Some(86_600));
+	}
+
+	#[test]
+	fn huge_repeats_stop_instead_of_overflowing() {
+		let tz = chrono_tz::Europe::Paris;
+		assert_eq!(
+			Recurrence::Interval(20_000_000 * 7 * DAY).next_after(100, 100, tz),
+			None
+		);
+		assert_eq!(Recurrence::Interval(i64::MAX - 1).next_after(100, 100, tz), None);
+		assert_eq!(
+			Recurrence::Interval(7 * DAY).next_after(100, 100, tz),
+			Some(100 + 7 * DAY)
+		);
+	}
+
+	#[test]
+	fn reminders_edited_while_sending_are_left_alone() {
+		let db = Db::open(":memory:").unwrap();
+		let once = db.add_reminder(&reminder(100, "once", None)).unwrap();
+		let every = (db.add_reminder(&reminder(100, "every day", Some(Recurrence::Interval(DAY))))).unwrap();
+
+		// both got moved by their owner while being sent
+		for id in [once, every] {
+			let mut edited = db.user_reminder(UserId::new(1), id).unwrap().unwrap();
+			edited.timestamp = 500;
+			db.update_reminder(&edited).unwrap();
+		}
+
+		let sent = [
+			(once, 100, AfterSending::Delete),
+			(every, 100, AfterSending::Reschedule(100 + DAY)),
+		];
+		db.finish_reminders(&sent).unwrap();
+
+		let timestamps: Vec<_> = (db.user_reminders(UserId::new(1)).unwrap().iter())
+			.map(|reminder| (reminder.id, reminder.timestamp))
+			.collect();
+		assert_eq!(timestamps, [(once, 500), (every, 500)]);
+	}
+
+	#[test]
+	fn excerpts_cut_on_char_boundaries() {
+		assert_eq!(excerpt("short", 10), "short");
+		assert_eq!(excerpt("two\nlines", 10), "two lines");
+		assert_eq!(excerpt("ééééééééééé", 10), "ééééééé...");
+		assert_eq!(excerpt("♡♡♡♡♡♡♡♡♡♡", 10), "♡♡♡♡♡♡♡♡♡♡");
+	}
 }
@@ -0,0 +1,567 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+use regex::{Regex, RegexBuilder};
+use serenity::all::{Channel, ChannelId, CurrentUser, GuildId, MessageUpdateEvent, UserId};
+use serenity::builder::CreateMessage;
+use serenity::model::prelude::Message;
+use serenity::prelude::Context;
+use tokio::time::sleep;
+
+use crate::db::Db;
+use crate::mentions::channel_permissions;
+use crate::modlog;
+use crate::reminders::date_time_now;
+use crate::soliloquy;
+use crate::strikes::{self, Strike};
+
+/// Kinds of rules, as stored in the database, with their name in /channelrules.
+pub const KINDS: &[(&str, &str)] = &[
+	("no_mentions", "No mentions"),
+	("no_replies", "No replies"),
+	("no_links", "No links"),
+	("attachments_only", "Attachments only"),
+	("text_only", "Text only"),
+	("no_embeds", "No embeds"),
+	("max_length", "Max length"),
+	("slowmode", "One message per N minutes"),
+	("deny", "Deny list"),
+];
+
+/// Biggest a deny list regex can get once compiled, so nobody can make me chew on a huge one for every message.
+const MAX_REGEX_SIZE: usize = 1 << 16;
+
+/// Something messages in a channel have to follow.
+#[derive(Clone, Debug)]
+pub enum Rule {
+	NoMentions,
+	/// Replies are still fine for `[meta]` messages.
+	NoReplies,
+	NoLinks,
+	AttachmentsOnly,
+	TextOnly,
+	NoEmbeds,
+	MaxLength(usize),
+	Slowmode {
+		minutes: i64,
+	},
+	/// Case insensitive.
+	Deny(Regex),
+}
+
+/// A rule of a channel, with what to tell whoever breaks it.
+#[derive(Clone, Debug)]
+pub struct ChannelRule {
+	pub id: i64,
+	pub channel_id: ChannelId,
+	pub rule: Rule,
+	/// Uses the rule's default message if not set.
+	pub message: Option<String>,
+	/// Whether it's also enforced in the threads and forum posts of the channel.
+	pub threads: bool,
+}
+
+impl Rule {
+	/// Reads a rule from its kind and value. Errors explain what's wrong with the value.
+	pub fn parse(kind: &str, value: Option<&str>) -> Result<Self, String> {
+		let value = value.map(str::trim).filter(|value| !value.is_empty());
+
+		let rule = match (kind, value) {
+			("no_mentions", _) => Rule::NoMentions,
+			("no_replies", _) => Rule::NoReplies,
+			("no_links", _) => Rule::NoLinks,
+			("attachments_only", _) => Rule::AttachmentsOnly,
+			("text_only", _) => Rule::TextOnly,
+			("no_embeds", _) => Rule::NoEmbeds,
+			("max_length", Some(value)) => match value.parse() {
+				Ok(len) if len > 0 => Rule::MaxLength(len),
+				_ => return Err(format!("`{value}` isn't a number of characters :c")),
+			},
+			("slowmode", Some(value)) => match value.parse() {
+				Ok(minutes) if minutes > 0 => Rule::Slowmode { minutes },
+				_ => return Err(format!("`{value}` isn't a number of minutes :c")),
+			},
+			("deny", Some(value)) => {
+				let regex = RegexBuilder::new(value)
+					.case_insensitive(true)
+					.size_limit(MAX_REGEX_SIZE)
+					.build()
+					.map_err(|_| format!("`{value}` isn't a regex I can use :c"))?;
+				Rule::Deny(regex)
+			}
+			("max_length" | "slowmode" | "deny", None) => {
+				return Err("This rule needs a `value`~".to_string());
+			}
+			(kind, _) => return Err(format!("I don't know any rule called `{kind}` :c")),
+		};
+
+		Ok(rule)
+	}
+
+	pub fn kind(&self) -> &'static str {
+		match self {
+			Rule::NoMentions => "no_mentions",
+			Rule::NoReplies => "no_replies",
+			Rule::NoLinks => "no_links",
+			Rule::AttachmentsOnly => "attachments_only",
+			Rule::TextOnly => "text_only",
+			Rule::NoEmbeds => "no_embeds",
+			Rule::MaxLength(_) => "max_length",
+			Rule::Slowmode { .. } => "slowmode",
+			Rule::Deny(_) => "deny",
+		}
+	}
+
+	pub fn value(&self) -> Option<String> {
+		match self {
+			Rule::MaxLength(len) => Some(len.to_string()),
+			Rule::Slowmode { minutes } => Some(minutes.to_string()),
+			Rule::Deny(regex) => Some(regex.as_str().to_string()),
+			_ => None,
+		}
+	}
+
+	/// What to tell whoever breaks the rule, unless the channel says otherwise.
+	pub fn default_message(&self, channel_id: ChannelId) -> String {
+		match self {
+			Rule::NoMentions => format!("Please, do not mention people in <#{channel_id}>!"),
+			Rule::NoReplies => format!("Please, do not reply to other messages in <#{channel_id}>!"),
+			Rule::NoLinks => format!("Please, do not post links in <#{channel_id}>!"),
+			Rule::AttachmentsOnly => format!("Please, only post attachments in <#{channel_id}>!"),
+			Rule::TextOnly => format!("Please, only post text in <#{channel_id}>, without attachments or stickers!"),
+			Rule::NoEmbeds => format!("Please, do not post embeds in <#{channel_id}>!"),
+			Rule::MaxLength(len) => format!("Please, keep your messages in <#{channel_id}> under {len} characters!"),
+			Rule::Slowmode { minutes } => {
+				format!("Please, only post once every {minutes} minutes in <#{channel_id}>!")
+			}
+			Rule::Deny(_) => format!("Sorry, that's not allowed in <#{channel_id}>!"),
+		}
+	}
+
+	/// Whether the message breaks the rule, given when its author last posted in the channel.
+	pub fn is_broken_by(&self, message: &Message, last_posted: Option<i64>, now: i64) -> bool {
+		match self {
+			Rule::NoMentions => {
+				!message.mentions.is_empty() || !message.mention_roles.is_empty() || message.mention_everyone
+			}
+			// do not match meta-messages
+			Rule::NoReplies => message.referenced_message.is_some() && !is_meta(&message.content),
+			Rule::NoLinks => message.content.split_whitespace().any(is_link),
+			Rule::AttachmentsOnly => message.attachments.is_empty(),
+			Rule::TextOnly => !message.attachments.is_empty() || !message.sticker_items.is_empty(),
+			Rule::NoEmbeds => !message.embeds.is_empty(),
+			Rule::MaxLength(len) => message.content.chars().count() > *len,
+			Rule::Slowmode { minutes } => last_posted.is_some_and(|last| now - last < minutes * 60),
+			Rule::Deny(regex) => regex.is_match(&message.content),
+		}
+	}
+}
+
+impl Display for Rule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Rule::NoMentions => write!(f, "no mentions"),
+			Rule::NoReplies => write!(f, "no replies"),
+			Rule::NoLinks => write!(f, "no links"),
+			Rule::AttachmentsOnly => write!(f, "attachments only"),
+			Rule::TextOnly => write!(f, "text only"),
+			Rule::NoEmbeds => write!(f, "no embeds"),
+			Rule::MaxLength(len) => write!(f, "at most {len} characters"),
+			Rule::Slowmode { minutes } => write!(f, "one message every {minutes} minutes"),
+			Rule::Deny(regex) => write!(f, "nothing matching `{}`", regex.as_str().replace('`', "ˋ")),
+		}
+	}
+}
+
+impl ChannelRule {
+	pub fn violation_message(&self) -> String {
+		(self.message.clone()).unwrap_or_else(|| self.rule.default_message(self.channel_id))
+	}
+
+	/// How the rule is shown to moderators.
+	pub fn name(&self) -> String {
+		match self.id {
+			// soliloquy rules aren't stored on their own
+			0 => format!("soliloquy: {}", self.rule),
+			id => format!("`{id}` {}", self.rule),
+		}
+	}
+}
+
+fn is_meta(content: &str) -> bool {
+	content.starts_with('[') && content.ends_with(']')
+}
+
+fn is_link(word: &str) -> bool {
+	let word = word.trim_start_matches(['<', '(']).to_ascii_lowercase();
+	word.starts_with("http://") || word.starts_with("https://") || word.starts_with("discord.gg/")
+}
+
+/// When each user last posted in each channel with a slowmode rule, if they followed the rules,
+/// and how long that channel's slowmode is.
+type LastPosted = HashMap<(ChannelId, UserId), (i64, i64)>;
+
+static LAST_POSTED: Mutex<Option<LastPosted>> = Mutex::new(None);
+
+/// First rule the message breaks, if any. Edited messages don't count as posting again.
+fn broken_rule<'a>(rules: &'a [ChannelRule], message: &Message, now: i64, edited: bool) -> Option<&'a ChannelRule> {
+	let mut last_posted = LAST_POSTED.lock().unwrap();
+	let last_posted = last_posted.get_or_insert_with(HashMap::new);
+	let key = (message.channel_id, message.author.id);
+
+	let last = (last_posted.get(&key)).filter(|_| !edited).map(|&(last, _)| last);
+	let broken = (rules.iter()).find(|rule| rule.rule.is_broken_by(message, last, now));
+
+	if broken.is_none() && !edited {
+		if let Some(longest) = (rules.iter())
+			.filter_map(|rule| match rule.rule {
+				Rule::Slowmode { minutes } => Some(minutes * 60),
+				_ => None,
+			})
+			.max()
+		{
+			// forget users who can post again anyway
+			last_posted.retain(|_, &mut (last, slowmode)| now - last < slowmode);
+			last_posted.insert(key, (now, longest));
+		}
+	}
+
+	broken
+}
+
+/// Parent of each thread, or `None` for channels that aren't threads, as that never changes.
+static PARENTS: Mutex<Option<HashMap<ChannelId, Option<ChannelId>>>> = Mutex::new(None);
+
+async fn parent_channel(ctx: &Context, channel_id: ChannelId) -> Option<ChannelId> {
+	if let Some(&parent) = (PARENTS.lock().unwrap().as_ref()).and_then(|parents| parents.get(&channel_id)) {
+		return parent;
+	}
+
+	let parent = match channel_id.to_channel(&ctx.http).await {
+		Ok(Channel::Guild(channel)) if channel.thread_metadata.is_some() => channel.parent_id,
+		Ok(_) => None,
+		Err(e) => {
+			tracing::error!("Could not fetch channel {channel_id}: {e}");
+			return None;
+		}
+	};
+
+	let mut parents = PARENTS.lock().unwrap();
+	parents.get_or_insert_with(HashMap::new).insert(channel_id, parent);
+	parent
+}
+
+/// Rules set on the channel itself, or only those also enforced in its threads.
+fn own_rules(db: &Db, channel_id: ChannelId, threads_only: bool) -> Vec<ChannelRule> {
+	let mut rules = Vec::new();
+	if let Some(threads) = db.soliloquy(channel_id).unwrap() {
+		if threads || !threads_only {
+			rules.extend(soliloquy::rules(channel_id, threads));
+		}
+	}
+
+	let channel_rules = db.channel_rules(channel_id).unwrap();
+	rules.extend(channel_rules.into_iter().filter(|rule| rule.threads || !threads_only));
+	rules
+}
+
+/// Rules of the channel, and those of its parent if it's a thread.
+async fn rules_of(db: &Db, ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> Vec<ChannelRule> {
+	let mut rules = own_rules(db, channel_id, false);
+
+	// only look for the parent when it matters, as that might need fetching the channel
+	if db.has_thread_rules(guild_id).unwrap() {
+		if let Some(parent) = parent_channel(ctx, channel_id).await {
+			rules.extend(own_rules(db, parent, true));
+		}
+	}
+
+	rules
+}
+
+pub async fn handle_message(db: &Db, bot: &RwLock<Option<CurrentUser>>, ctx: Context, message: Message) {
+	let Some(guild_id) = message.guild_id else {
+		return;
+	};
+
+	if message.author.id == bot.read().unwrap().as_ref().unwrap().id {
+		// ignore own messages
+		return;
+	}
+
+	let rules = rules_of(db, &ctx, guild_id, message.channel_id).await;
+	if let Some(rule) = broken_rule(&rules, &message, date_time_now().timestamp(), false) {
+		enforce(db, &ctx, guild_id, rule, &message, false).await;
+	}
+}
+
+/// Checks edited messages again, so nobody can sneak a ping or a reply in after posting.
+///
+/// `new` is the whole message when it was cached. Otherwise it's made from the update, as fetching it
+/// would need Read Message History, which AI-chan doesn't ask for.
+pub async fn handle_message_update(
+	db: &Db,
+	bot: &RwLock<Option<CurrentUser>>,
+	ctx: Context,
+	new: Option<Message>,
+	event: MessageUpdateEvent,
+) {
+	let Some(guild_id) = event.guild_id else {
+		return;
+	};
+
+	let bot_id = bot.read().unwrap().as_ref().unwrap().id;
+	if event.author.as_ref().is_some_and(|author| author.id == bot_id) {
+		// ignore own messages
+		return;
+	}
+
+	let rules = rules_of(db, &ctx, guild_id, event.channel_id).await;
+	if rules.is_empty() {
+		return;
+	}
+
+	let Some(message) = new.or_else(|| message_from_update(&event)) else {
+		return;
+	};
+
+	if message.author.id == bot_id {
+		return;
+	}
+
+	if let Some(rule) = broken_rule(&rules, &message, date_time_now().timestamp(), true) {
+		enforce(db, &ctx, guild_id, rule, &message, true).await;
+	}
+}
+
+/// The edited message, if the update has all of it.
+///
+/// Edits by users come with the whole message, but updates by Discord itself, like link previews
+/// being added, only have what changed and no author.
+fn message_from_update(event: &MessageUpdateEvent) -> Option<Message> {
+	if event.author.is_none() || event.content.is_none() {
+		return None;
+	}
+
+	let mut message = Message::default();
+	event.apply_to_message(&mut message);
+	Some(message)
+}
+
+/// How the author of a message breaking a rule was told about it.
+#[derive(Clone, Copy, Debug)]
+pub enum Notified {
+	Dm,
+	Reply,
+	Nobody,
+}
+
+/// What was done about a message breaking a rule.
+pub struct Enforcement<'a> {
+	pub rule: &'a ChannelRule,
+	pub edited: bool,
+	pub deleted: bool,
+	pub notified: Notified,
+	/// None if they didn't get one, as the message is still there or they can't be struck.
+	pub strike: Option<Strike>,
+	pub timed_out: bool,
+}
+
+/// Deletes the message, gives its author a strike and tells them why, and logs it for the moderators.
+async fn enforce(db: &Db, ctx: &Context, guild_id: GuildId, rule: &ChannelRule, message: &Message, edited: bool) {
+	let author = message.author.id;
+	let deleted = delete(ctx, message).await;
+
+	let strike = match deleted && can_strike(ctx, message).await {
+		true => Some(strikes::record(
+			db,
+			guild_id,
+			author,
+			&rule.name(),
+			date_time_now().timestamp(),
+		)),
+		false => None,
+	};
+
+	let oops_msg = match &strike {
+		Some(strike) => format!("{}\n{}", rule.violation_message(), strike.warning()),
+		None => rule.violation_message(),
+	};
+	let notified = oops(&oops_msg, ctx, message).await;
+
+	let timed_out = match &strike {
+		Some(strike) => strikes::punish(ctx, guild_id, author, strike).await,
+		None => false,
+	};
+
+	let enforcement = Enforcement {
+		rule,
+		edited,
+		deleted,
+		notified,
+		strike,
+		timed_out,
+	};
+	modlog::log(db, ctx, guild_id, modlog::enforcement_embed(message, &enforcement)).await;
+
+	if let Some(strike) = strike.filter(|strike| strike.notify) {
+		modlog::log(db, ctx, guild_id, modlog::repeat_offender_embed(message, &strike)).await;
+	}
+}
+
+/// Returns whether the message could be deleted.
+async fn delete(ctx: &Context, message: &Message) -> bool {
+	match message.delete(&ctx.http).await {
+		Ok(()) => true,
+		Err(e) => {
+			tracing::error!("Could not delete message: {}", e);
+			false
+		}
+	}
+}
+
+/// Whether the author of the message gets strikes. Bots, webhooks and moderators don't.
+async fn can_strike(ctx: &Context, message: &Message) -> bool {
+	if message.author.bot || message.webhook_id.is_some() {
+		return false;
+	}
+
+	match channel_permissions(ctx, message.author.id, message.channel_id).await {
+		Ok(Some((permissions, _))) => !permissions.moderate_members() && !permissions.administrator(),
+		Ok(None) => false,
+		Err(e) => {
+			tracing::error!("Cannot check permissions of {}: {e}", message.author.id);
+			false
+		}
+	}
+}
+
+/// Tells the author of the message why it was deleted, with their message so they don't lose it.
+/// Returns how they were told.
+async fn oops(oops_msg: &str, ctx: &Context, message: &Message) -> Notified {
+	// zero-width space nyehehehe
+	let sanitized_message_content = message.content.replace('`', "\u{200B}`");
+
+	let you_shall_not_pass = format!(
+		"{}\n\n*Original message~*\n```\n{}\n```",
+		oops_msg, &sanitized_message_content
+	);
+
+	let content = CreateMessage::new().content(&you_shall_not_pass);
+
+	if message.author.dm(&ctx.http, content).await.is_ok() {
+		// try DM first
+		tracing::info!("Sent a DM to {}", message.author);
+		Notified::Dm
+	} else if let Ok(response) = message.reply_ping(&ctx.http, you_shall_not_pass).await {
+		// try in-channel
+		tracing::info!("Replied to {}", message.author);
+
+		let http = ctx.http.clone();
+		tokio::spawn(async move {
+			sleep(Duration::from_secs(7)).await;
+			if let Err(e) = response.delete(&http).await {
+				tracing::error!("Could not delete response: {}", e);
+			}
+		});
+		Notified::Reply
+	} else {
+		// give up :(
+		tracing::warn!("Could not send a message to {}. I give up :c", message.author);
+		Notified::Nobody
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serenity::all::User;
+
+	use super::*;
+
+	fn message(content: &str) -> Message {
+		let mut message = Message::default();
+		message.content = content.to_string();
+		message
+	}
+
+	fn rule(kind: &str, value: Option<&str>) -> Rule {
+		Rule::parse(kind, value).unwrap()
+	}
+
+	#[test]
+	fn rules() {
+		let mut mention = message("hi");
+		mention.mentions.push(User::default());
+		assert!(rule("no_mentions", None).is_broken_by(&mention, None, 0));
+		assert!(!rule("no_mentions", None).is_broken_by(&message("hi"), None, 0));
+
+		let mut reply = message("me too");
+		reply.referenced_message = Some(Box::new(message("hi")));
+		assert!(rule("no_replies", None).is_broken_by(&reply, None, 0));
+		reply.content = "[sorry, wrong channel]".to_string();
+		assert!(!rule("no_replies", None).is_broken_by(&reply, None, 0));
+
+		assert!(rule("no_links", None).is_broken_by(&message("look <HTTPS://example.com>"), None, 0));
+		assert!(!rule("no_links", None).is_broken_by(&message("https is a protocol"), None, 0));
+
+		assert!(rule("attachments_only", None).is_broken_by(&message("hi"), None, 0));
+		assert!(!rule("text_only", None).is_broken_by(&message("hi"), None, 0));
+
+		assert!(rule("max_length", Some("5")).is_broken_by(&message("hello!"), None, 0));
+		assert!(!rule("max_length", Some("5")).is_broken_by(&message("héllo"), None, 0));
+
+		assert!(rule("slowmode", Some("2")).is_broken_by(&message("hi"), Some(100), 219));
+		assert!(!rule("slowmode", Some("2")).is_broken_by(&message("hi"), Some(100), 220));
+		assert!(!rule("slowmode", Some("2")).is_broken_by(&message("hi"), None, 220));
+
+		assert!(rule("deny", Some("ba+d")).is_broken_by(&message("so BAAAD"), None, 0));
+		assert!(!rule("deny", Some("ba+d")).is_broken_by(&message("good"), None, 0));
+
+		assert!(Rule::parse("max_length", Some("lots")).is_err());
+		assert!(Rule::parse("slowmode", None).is_err());
+		assert!(Rule::parse("deny", Some("(unclosed")).is_err());
+		assert!(Rule::parse("no_fun", None).is_err());
+	}
+
+	#[test]
+	fn slowmode_only_counts_accepted_messages_and_not_edits() {
+		let channel_id = ChannelId::new(22);
+		let rules = [
+			ChannelRule {
+				id: 1,
+				channel_id,
+				rule: rule("slowmode", Some("1")),
+				message: None,
+				threads: false,
+			},
+			ChannelRule {
+				id: 2,
+				channel_id,
+				rule: rule("no_links", None),
+				message: Some("No links!".to_string()),
+				threads: false,
+			},
+		];
+
+		let mut first = message("hi");
+		first.channel_id = channel_id;
+		let mut link = message("https://example.com");
+		link.channel_id = channel_id;
+
+		assert!(broken_rule(&rules, &first, 1000, false).is_none());
+		assert_eq!(broken_rule(&rules, &first, 1030, false).map(|rule| rule.id), Some(1));
+		assert_eq!(broken_rule(&rules, &link, 1060, false).map(|rule| rule.id), Some(2));
+		assert!(broken_rule(&rules, &first, 1061, false).is_none());
+		assert_eq!(
+			broken_rule(&rules, &first, 1062, false).unwrap().violation_message(),
+			rules[0].rule.default_message(channel_id)
+		);
+
+		// editing a message in doesn't count as posting again, but the other rules still apply
+		assert!(broken_rule(&rules, &first, 1070, true).is_none());
+		assert_eq!(broken_rule(&rules, &link, 1071, true).map(|rule| rule.id), Some(2));
+		assert!(broken_rule(&rules, &first, 1121, false).is_none());
+	}
+}
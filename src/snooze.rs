@@ -0,0 +1,136 @@
+use chrono::{NaiveTime, TimeDelta, TimeZone};
+use serenity::all::{
+	ButtonStyle, ComponentInteraction, Context, CreateActionRow, CreateButton, CreateInteractionResponse,
+	CreateInteractionResponseMessage, UserId,
+};
+
+use crate::db::Db;
+use crate::delivery::Delivery;
+use crate::limits::{self, Limits};
+use crate::mentions::Mentions;
+use crate::reminders::{date_time_now, Reminder};
+use crate::timezones::{local_timestamp, user_timezone};
+
+/// Prefix of the custom IDs of the buttons attached to delivered reminders.
+pub const CUSTOM_ID_PREFIX: &str = "snooze:";
+
+/// How long the buttons of a delivered reminder keep working.
+pub const SNOOZABLE_FOR_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Time of day "Snooze until tomorrow" brings the reminder back at.
+const TOMORROW_TIME: NaiveTime = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+
+/// Buttons to attach to a delivered reminder, only usable by its owner.
+///
+/// `snoozable_id` is where its text was saved with [`Db::add_snoozable`].
+pub fn buttons(user_id: UserId, snoozable_id: i64) -> Vec<CreateActionRow> {
+	let button = |action: &str, label: &str| {
+		CreateButton::new(format!("{CUSTOM_ID_PREFIX}{action}:{user_id}:{snoozable_id}")).label(label)
+	};
+
+	vec![CreateActionRow::Buttons(vec![
+		button("10m", "Snooze 10m").style(ButtonStyle::Secondary),
+		button("1h", "Snooze 1h").style(ButtonStyle::Secondary),
+		button("tomorrow", "Snooze until tomorrow").style(ButtonStyle::Secondary),
+		button("done", "Done").style(ButtonStyle::Success),
+	])]
+}
+
+pub async fn handle_component(db: &Db, limits: &Limits, ctx: &Context, component: &ComponentInteraction) {
+	let custom_id = &component.data.custom_id;
+	let mut parts = custom_id.strip_prefix(CUSTOM_ID_PREFIX).unwrap_or_default().split(':');
+	let (Some(action), Some(owner)) = (parts.next(), parts.next()) else {
+		tracing::error!("Invalid snooze button {custom_id:?}");
+		return;
+	};
+
+	let Ok(owner) = owner.parse::<UserId>() else {
+		tracing::error!("Invalid snooze button owner {owner:?}");
+		return;
+	};
+
+	if component.user.id != owner {
+		respond_ephemeral(
+			ctx,
+			component,
+			format!("Only <@{owner}> can use these buttons, this isn't your reminder~"),
+		)
+		.await;
+		return;
+	}
+
+	// buttons from before their text was saved have no ID, and old ones were forgotten
+	let snoozable_id = parts.next().and_then(|id| id.parse::<i64>().ok());
+	let Some((snoozable_id, message)) = snoozable_id.and_then(|id| Some((id, db.snoozable(id, owner).unwrap()?)))
+	else {
+		respond_ephemeral(ctx, component, "This reminder is too old to snooze now :c".to_string()).await;
+		return;
+	};
+
+	let now = date_time_now();
+	let tz = user_timezone(db, owner);
+
+	let timestamp = match action {
+		"10m" => Some((now + TimeDelta::minutes(10)).timestamp()),
+		"1h" => Some((now + TimeDelta::hours(1)).timestamp()),
+		"tomorrow" => {
+			let today = tz.from_utc_datetime(&now.naive_utc()).date_naive();
+			Some(local_timestamp(tz, today.succ_opt().unwrap().and_time(TOMORROW_TIME)))
+		}
+		"done" => None,
+		action => {
+			tracing::error!("Invalid snooze action {action:?}");
+			return;
+		}
+	};
+
+	let mut content = component.message.content.clone();
+
+	if let Some(timestamp) = timestamp {
+		let reminder = Reminder {
+			id: 0,
+			timestamp,
+			user_id: owner,
+			channel_id: component.channel_id,
+			guild_id: component.guild_id,
+			message,
+			recurrence: None,
+			// back where the button was pressed, be it the channel or DMs
+			deliver: Delivery::Channel,
+			mentions: Mentions::default(),
+			subscribers: Vec::new(),
+			post: None,
+			retry: None,
+		};
+
+		if let Err(e) = limits.check_new(db, std::slice::from_ref(&reminder), now.timestamp()) {
+			respond_ephemeral(ctx, component, limits::error_message(&e)).await;
+			return;
+		}
+
+		let id = db.add_reminder(&reminder).unwrap();
+
+		content += &format!("\n-# Snoozed until <t:{timestamp}:F> (`{id}`)");
+	} else {
+		content += "\n-# Done~ ♡";
+	}
+
+	// the buttons go away with this
+	db.delete_snoozable(snoozable_id).unwrap();
+
+	let response_message = CreateInteractionResponseMessage::new()
+		.content(content)
+		.components(vec![]);
+	let builder = CreateInteractionResponse::UpdateMessage(response_message);
+	if let Err(e) = component.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to button: {e}");
+	}
+}
+
+async fn respond_ephemeral(ctx: &Context, component: &ComponentInteraction, content: String) {
+	let response_message = CreateInteractionResponseMessage::new().ephemeral(true).content(content);
+	let builder = CreateInteractionResponse::Message(response_message);
+	if let Err(e) = component.create_response(&ctx.http, builder).await {
+		tracing::error!("Cannot respond to button: {e}");
+	}
+}
@@ -0,0 +1,157 @@
+use chrono::DateTime;
+use serenity::all::{Context, EditMember, GuildId, UserId};
+
+use crate::db::Db;
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// How strikes for breaking channel rules add up in a server, and what happens as they do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StrikePolicy {
+	/// How long a strike counts.
+	pub decay_days: i64,
+	/// From how many strikes users get timed out, if ever.
+	pub timeout_after: Option<usize>,
+	pub timeout_minutes: i64,
+	/// At how many strikes moderators get told, if ever.
+	pub notify_after: Option<usize>,
+}
+
+impl Default for StrikePolicy {
+	fn default() -> Self {
+		Self {
+			decay_days: 30,
+			timeout_after: Some(3),
+			timeout_minutes: 10,
+			notify_after: Some(5),
+		}
+	}
+}
+
+impl StrikePolicy {
+	/// Oldest strikes still counting.
+	pub fn since(&self, now: i64) -> i64 {
+		now.saturating_sub(self.decay_days.saturating_mul(DAY_SECS))
+	}
+
+	/// What having that many strikes leads to.
+	pub fn strike(&self, count: usize, now: i64) -> Strike {
+		let timed_out = self.timeout_after.is_some_and(|after| count >= after);
+
+		Strike {
+			count,
+			timeout_until: timed_out.then(|| now + self.timeout_minutes * 60),
+			until_timeout: (self.timeout_after).filter(|_| !timed_out).map(|after| after - count),
+			// only once, not on every strike after that
+			notify: self.notify_after == Some(count),
+		}
+	}
+}
+
+/// A strike a user just got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Strike {
+	/// Strikes the user has now, this one included.
+	pub count: usize,
+	/// Until when they get timed out, if they do.
+	pub timeout_until: Option<i64>,
+	/// How many more strikes until they get timed out, if they don't yet.
+	pub until_timeout: Option<usize>,
+	/// Whether moderators should hear about it.
+	pub notify: bool,
+}
+
+impl Strike {
+	/// What to tell the user about it.
+	pub fn warning(&self) -> String {
+		let count = self.count;
+		match (self.timeout_until, self.until_timeout) {
+			(Some(until), _) => format!("That's strike {count}, so you're timed out until <t:{until}:t> :c"),
+			(None, Some(1)) => format!("That's strike {count}! Once more and you'll be timed out :c"),
+			(None, Some(left)) => format!("That's strike {count}! {left} more and you'll be timed out :c"),
+			(None, None) => format!("That's strike {count} :c"),
+		}
+	}
+}
+
+/// Gives the user a strike for breaking the rule.
+pub fn record(db: &Db, guild_id: GuildId, user_id: UserId, rule: &str, now: i64) -> Strike {
+	let policy = db.strike_policy(guild_id).unwrap();
+	let count = db.add_strike(guild_id, user_id, now, rule, policy.since(now)).unwrap();
+	policy.strike(count, now)
+}
+
+/// Times the user out if the strike calls for it. Returns whether they were.
+pub async fn punish(ctx: &Context, guild_id: GuildId, user_id: UserId, strike: &Strike) -> bool {
+	let Some(until) = strike
+		.timeout_until
+		.and_then(|until| DateTime::from_timestamp(until, 0))
+	else {
+		return false;
+	};
+
+	let timeout = EditMember::new().disable_communication_until_datetime(until.into());
+	match guild_id.edit_member(&ctx.http, user_id, timeout).await {
+		Ok(_) => true,
+		Err(e) => {
+			tracing::error!("Cannot time out {user_id}: {e}");
+			false
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn strikes_escalate_and_decay() {
+		let db = Db::open(":memory:").unwrap();
+		let guild_id = GuildId::new(1);
+		let user_id = UserId::new(2);
+		let policy = StrikePolicy {
+			decay_days: 1,
+			timeout_after: Some(2),
+			timeout_minutes: 5,
+			notify_after: Some(3),
+		};
+		db.set_strike_policy(guild_id, &policy).unwrap();
+		assert_eq!(db.strike_policy(guild_id).unwrap(), policy);
+
+		let strike = |now| record(&db, guild_id, user_id, "no links", now);
+		assert_eq!(
+			strike(1000),
+			Strike {
+				count: 1,
+				timeout_until: None,
+				until_timeout: Some(1),
+				notify: false,
+			}
+		);
+		assert_eq!(strike(2000).timeout_until, Some(2300));
+		assert!(strike(3000).notify);
+		assert!(!policy.strike(4, 4000).notify);
+		let forever = StrikePolicy {
+			decay_days: i64::MAX,
+			..policy
+		};
+		assert!(forever.since(1000) < 0);
+
+		// the first one is forgotten after a day
+		assert_eq!(strike(1000 + DAY_SECS + 1).count, 3);
+		assert_eq!(db.strikes(guild_id, user_id, 0).unwrap().len(), 3);
+
+		assert_eq!(db.clear_strikes(guild_id, user_id).unwrap(), 3);
+		assert_eq!(strike(100_000).count, 1);
+
+		// steps can be turned off
+		let lenient = StrikePolicy {
+			timeout_after: None,
+			notify_after: None,
+			..policy
+		};
+		db.set_strike_policy(guild_id, &lenient).unwrap();
+		assert_eq!(db.strike_policy(guild_id).unwrap(), lenient);
+		assert_eq!(lenient.strike(50, 0).warning(), "That's strike 50 :c");
+	}
+}
@@ -0,0 +1,910 @@
+use std::{num::ParseIntError, ops::Range};
+
+use chrono::{
+	DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Timelike, Weekday,
+};
+use chrono_tz::Tz;
+
+use crate::reminders::{Recurrence, MAX_REPEAT_SECS};
+use crate::timezones::local_timestamp;
+
+/// Smallest interval a reminder can repeat at, so nobody floods a channel by accident.
+const MIN_REPEAT_SECS: i64 = 60;
+
+/// What `eod` means: the end of a work day.
+const END_OF_DAY: NaiveTime = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+
+#[derive(Debug, PartialEq)]
+pub enum TimeExprErrorKind {
+	Empty,
+	UnknownWord,
+	UnknownUnit,
+	MissingUnit,
+	MissingTime,
+	MissingWeekday,
+
+	DuplicateDay,
+	DuplicateTime,
+
+	UnrecognizedDateFormat,
+	UnrecognizedTimeFormat,
+
+	ParseNumber(ParseIntError),
+	ParseYear(ParseIntError),
+	ParseMonth(ParseIntError),
+	ParseDay(ParseIntError),
+
+	InvalidDate,
+	SkippedTime,
+	OutOfRange,
+
+	InvalidMonth,
+	InvalidDay,
+
+	ParseHour(ParseIntError),
+	ParseMin(ParseIntError),
+	ParseSec(ParseIntError),
+
+	InvalidHour,
+	InvalidMin,
+	InvalidSec,
+
+	MissingEvery,
+	UnrecognizedRule,
+	TooOften,
+}
+
+/// Error while parsing a time expression, along with the byte range of the input it comes from.
+#[derive(Debug, PartialEq)]
+pub struct TimeExprError {
+	pub kind: TimeExprErrorKind,
+	pub span: Range<usize>,
+}
+
+impl TimeExprErrorKind {
+	fn at(self, span: Range<usize>) -> TimeExprError {
+		TimeExprError { kind: self, span }
+	}
+}
+
+/// Explains what went wrong, and points at the part of the input that doesn't make sense.
+pub fn error_message(e: &TimeExprError, input: &str) -> String {
+	let message = match &e.kind {
+		TimeExprErrorKind::Empty => "You didn't tell me when!".to_string(),
+		TimeExprErrorKind::UnknownWord => "I don't know what this means :c".to_string(),
+		TimeExprErrorKind::UnknownUnit => "I don't know this unit!".to_string(),
+		TimeExprErrorKind::MissingUnit => "A number of what? Give me a unit, like `10m` or `3 days`!".to_string(),
+		TimeExprErrorKind::MissingTime => "At what time?".to_string(),
+		TimeExprErrorKind::MissingWeekday => {
+			"I only know weekdays after `this`, and weekdays, `week`, `month` or `year` after `next`!".to_string()
+		}
+		TimeExprErrorKind::DuplicateDay => "You already told me which day!".to_string(),
+		TimeExprErrorKind::DuplicateTime => "You already told me what time!".to_string(),
+		TimeExprErrorKind::UnrecognizedDateFormat => {
+			"I don't recognize this date format! I only know `YYYY-MM-DD`.".to_string()
+		}
+		TimeExprErrorKind::UnrecognizedTimeFormat => {
+			"I don't recognize this time format! I only know `hh:mm`, `hh:mm:ss` and things like `9am`.".to_string()
+		}
+		TimeExprErrorKind::ParseNumber(pie) => format!("Was that a number? I don't get it :c\n`{pie}`"),
+		TimeExprErrorKind::ParseYear(pie) => format!("Was that a number for the year? I don't get it :c\n`{pie}`"),
+		TimeExprErrorKind::ParseMonth(pie) => {
+			format!("Was that a number for the month? I don't get it :c\n`{pie}`")
+		}
+		TimeExprErrorKind::ParseDay(pie) => format!("Was that a number for the day? I don't get it :c\n`{pie}`"),
+		TimeExprErrorKind::InvalidDate => "This date is invalid!".to_string(),
+		TimeExprErrorKind::SkippedTime => {
+			"This time doesn't exist in your time zone, the clocks skip right over it that day!".to_string()
+		}
+		TimeExprErrorKind::OutOfRange => "That's way too far away, even for me!".to_string(),
+		TimeExprErrorKind::InvalidMonth => {
+			"This month is invalid! There is no more of them after December~".to_string()
+		}
+		TimeExprErrorKind::InvalidDay => "This day is invalid! There are never more than 31 days~".to_string(),
+		TimeExprErrorKind::ParseHour(pie) => {
+			format!("Was that a number for the hours? I don't get it :c\n`{pie}`")
+		}
+		TimeExprErrorKind::ParseMin(pie) => {
+			format!("Was that a number for the minutes? I don't get it :c\n`{pie}`")
+		}
+		TimeExprErrorKind::ParseSec(pie) => {
+			format!("Was that a number for the seconds? I don't get it :c\n`{pie}`")
+		}
+		TimeExprErrorKind::InvalidHour => {
+			"This hour is invalid! I don't know how to count after 23, tehe :P".to_string()
+		}
+		TimeExprErrorKind::InvalidMin => "This minute is invalid!".to_string(),
+		TimeExprErrorKind::InvalidSec => "This second is invalid!".to_string(),
+		TimeExprErrorKind::MissingEvery => "Repeat rules start with `every`!".to_string(),
+		TimeExprErrorKind::UnrecognizedRule => "I don't recognize this repeat rule!".to_string(),
+		TimeExprErrorKind::TooOften => {
+			"That's way too often! Reminders can repeat at most once per minute~".to_string()
+		}
+	};
+
+	// the span is in bytes of the input as it was given, so count characters before changing it
+	let start = input.get(..e.span.start).map_or(0, |before| before.chars().count());
+	let len = input.get(e.span.clone()).map_or(0, |span| span.chars().count()).max(1);
+
+	// replace backticks with grave accent to avoid breaking the code block
+	let input = input.replace('`', "ˋ").replace(['\n', '\t'], " ");
+
+	format!("{message}\n```\n{input}\n{}{}\n```", " ".repeat(start), "^".repeat(len))
+}
+
+struct Token {
+	text: String,
+	span: Range<usize>,
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+	let mut tokens = Vec::new();
+	let mut start = None;
+
+	for (i, c) in input.char_indices().chain([(input.len(), ' ')]) {
+		let is_separator = c.is_whitespace() || c == ',';
+		match start {
+			Some(s) if is_separator => {
+				tokens.push(Token {
+					text: input[s..i].to_lowercase(),
+					span: s..i,
+				});
+				start = None;
+			}
+			None if !is_separator => start = Some(i),
+			_ => {}
+		}
+	}
+
+	tokens
+}
+
+enum Day {
+	Today,
+	Tomorrow,
+	Weekday { weekday: Weekday, strict: bool },
+	Date(NaiveDate),
+	Later { months: u32, days: u64 },
+}
+
+enum Unit {
+	Fixed(TimeDelta),
+	Months(u32),
+}
+
+fn parse_unit(input: &str) -> Option<Unit> {
+	match input {
+		"s" | "sec" | "secs" | "second" | "seconds" => Some(Unit::Fixed(TimeDelta::seconds(1))),
+		"m" | "min" | "mins" | "minute" | "minutes" => Some(Unit::Fixed(TimeDelta::minutes(1))),
+		"h" | "hr" | "hrs" | "hour" | "hours" => Some(Unit::Fixed(TimeDelta::hours(1))),
+		"d" | "day" | "days" => Some(Unit::Fixed(TimeDelta::days(1))),
+		"w" | "wk" | "wks" | "week" | "weeks" => Some(Unit::Fixed(TimeDelta::weeks(1))),
+		"mo" | "mos" | "month" | "months" => Some(Unit::Months(1)),
+		"y" | "yr" | "yrs" | "year" | "years" => Some(Unit::Months(12)),
+		_ => None,
+	}
+}
+
+fn parse_weekday(input: &str) -> Option<Weekday> {
+	match input {
+		"mon" | "monday" => Some(Weekday::Mon),
+		"tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+		"wed" | "wednesday" => Some(Weekday::Wed),
+		"thu" | "thurs" | "thursday" => Some(Weekday::Thu),
+		"fri" | "friday" => Some(Weekday::Fri),
+		"sat" | "saturday" => Some(Weekday::Sat),
+		"sun" | "sunday" => Some(Weekday::Sun),
+		_ => None,
+	}
+}
+
+fn parse_named_time(input: &str) -> Option<NaiveTime> {
+	match input {
+		"noon" => NaiveTime::from_hms_opt(12, 0, 0),
+		"midnight" => Some(NaiveTime::MIN),
+		"eod" => Some(END_OF_DAY),
+		_ => None,
+	}
+}
+
+/// Parses a duration like `3h`, `2 weeks` or `an hour` at the start of the tokens.
+///
+/// Returns the duration and how many tokens it used, or `None` if the tokens don't start with a duration.
+fn parse_duration(tokens: &[Token]) -> Result<Option<(Unit, usize)>, TimeExprError> {
+	let token = &tokens[0];
+	let next_unit = tokens.get(1).and_then(|next| parse_unit(&next.text));
+
+	if token.text == "a" || token.text == "an" {
+		return Ok(next_unit.map(|unit| (unit, 2)));
+	}
+
+	let digits_len = token.text.chars().take_while(char::is_ascii_digit).count();
+	if digits_len == 0 {
+		return Ok(None);
+	}
+
+	let n: u32 =
+		(token.text[..digits_len].parse()).map_err(|pie| TimeExprErrorKind::ParseNumber(pie).at(token.span.clone()))?;
+
+	let (unit, used) = match &token.text[digits_len..] {
+		"" => match next_unit {
+			Some(unit) => (unit, 2),
+			None => return Err(TimeExprErrorKind::MissingUnit.at(token.span.clone())),
+		},
+		suffix => match parse_unit(suffix) {
+			Some(unit) => (unit, 1),
+			None => return Err(TimeExprErrorKind::UnknownUnit.at(token.span.clone())),
+		},
+	};
+
+	let span = token.span.start..tokens[used - 1].span.end;
+	let unit = match unit {
+		Unit::Fixed(delta) => (i32::try_from(n).ok())
+			.and_then(|n| delta.checked_mul(n))
+			.map(Unit::Fixed),
+		Unit::Months(months) => months.checked_mul(n).map(Unit::Months),
+	};
+
+	match unit {
+		Some(unit) => Ok(Some((unit, used))),
+		None => Err(TimeExprErrorKind::OutOfRange.at(span)),
+	}
+}
+
+/// Parses a time of day like `17:30`, `9:15:00`, `9pm` or `9 pm` at the start of the tokens.
+///
+/// A lone number like `9` is only read as an hour if `bare_hour` is set, which is the case after `at`.
+/// Returns the time, and how many tokens it used.
+fn parse_clock(tokens: &[Token], bare_hour: bool) -> Result<Option<(NaiveTime, usize)>, TimeExprError> {
+	let token = &tokens[0];
+
+	let (clock, meridiem, used) = match (token.text.strip_suffix("am"), token.text.strip_suffix("pm")) {
+		(Some(clock), _) if !clock.is_empty() => (clock, Some(false), 1),
+		(_, Some(clock)) if !clock.is_empty() => (clock, Some(true), 1),
+		_ => match tokens.get(1).map(|next| next.text.as_str()) {
+			Some("am") => (token.text.as_str(), Some(false), 2),
+			Some("pm") => (token.text.as_str(), Some(true), 2),
+			_ => (token.text.as_str(), None, 1),
+		},
+	};
+
+	let span = token.span.start..tokens[used - 1].span.end;
+
+	if !clock.starts_with(|c: char| c.is_ascii_digit()) {
+		return Ok(None);
+	}
+
+	let time = if clock.contains(':') {
+		parse_time_of_day(clock).map_err(|kind| kind.at(span.clone()))?
+	} else if meridiem.is_some() || bare_hour {
+		let hour: u32 = clock
+			.parse()
+			.map_err(|pie| TimeExprErrorKind::ParseHour(pie).at(span.clone()))?;
+		if hour >= 24 {
+			return Err(TimeExprErrorKind::InvalidHour.at(span));
+		}
+
+		NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+	} else {
+		return Ok(None);
+	};
+
+	let time = match meridiem {
+		None => time,
+		Some(pm) => {
+			let hour = match time.hour() {
+				0 | 13.. => return Err(TimeExprErrorKind::InvalidHour.at(span)),
+				12 => 0,
+				hour => hour,
+			};
+
+			let hour = if pm { hour + 12 } else { hour };
+			time.with_hour(hour).unwrap()
+		}
+	};
+
+	Ok(Some((time, used)))
+}
+
+fn parse_time_of_day(input: &str) -> Result<NaiveTime, TimeExprErrorKind> {
+	let time_part = input.trim().split(':').collect::<Vec<_>>();
+
+	let (hour, min, sec) = if let &[h, m, s] = time_part.as_slice() {
+		(h, m, s)
+	} else if let &[h, m] = time_part.as_slice() {
+		(h, m, "0")
+	} else {
+		return Err(TimeExprErrorKind::UnrecognizedTimeFormat);
+	};
+
+	let hour: u32 = hour.parse().map_err(TimeExprErrorKind::ParseHour)?;
+	let min: u32 = min.parse().map_err(TimeExprErrorKind::ParseMin)?;
+	let sec: u32 = sec.parse().map_err(TimeExprErrorKind::ParseSec)?;
+
+	if hour >= 24 {
+		return Err(TimeExprErrorKind::InvalidHour);
+	}
+	if min >= 60 {
+		return Err(TimeExprErrorKind::InvalidMin);
+	}
+	if sec >= 60 {
+		return Err(TimeExprErrorKind::InvalidSec);
+	}
+
+	Ok(NaiveTime::from_hms_opt(hour, min, sec).unwrap())
+}
+
+fn parse_date(input: &str) -> Result<NaiveDate, TimeExprErrorKind> {
+	let [year, month, day] = input.split('-').collect::<Vec<_>>()[..] else {
+		return Err(TimeExprErrorKind::UnrecognizedDateFormat);
+	};
+
+	let year: i32 = year.parse().map_err(TimeExprErrorKind::ParseYear)?;
+	let month: u32 = month.parse().map_err(TimeExprErrorKind::ParseMonth)?;
+	let day: u32 = day.parse().map_err(TimeExprErrorKind::ParseDay)?;
+
+	if month > 12 {
+		return Err(TimeExprErrorKind::InvalidMonth);
+	}
+	if day > 31 {
+		return Err(TimeExprErrorKind::InvalidDay);
+	}
+
+	NaiveDate::from_ymd_opt(year, month, day).ok_or(TimeExprErrorKind::InvalidDate)
+}
+
+fn resolve(tz: Tz, naive: NaiveDateTime, span: Range<usize>) -> Result<DateTime<Tz>, TimeExprError> {
+	// when clocks go back and the time happens twice, go with the first one
+	(tz.from_local_datetime(&naive).earliest()).ok_or(TimeExprErrorKind::SkippedTime.at(span))
+}
+
+/// Parses a time expression like `tomorrow at 9`, `next friday`, `in 2 weeks`, `17:30` or `2027-06-10 12:23`,
+/// relative to `now` and in the time zone of `now`.
+///
+/// Parts can be combined in any order: an optional day (`today`, `tomorrow`, a weekday, `next week`, a date),
+/// an optional time of day (`at 9`, `9pm`, `17:30`, `noon`, `midnight`, `eod`), or durations (`in 1d 3h`).
+/// A day without a time of day keeps the current time, and a time of day that already passed today means tomorrow.
+pub fn parse_time_expr(now: &DateTime<Tz>, input: &str) -> Result<DateTime<Tz>, TimeExprError> {
+	let tz = now.timezone();
+	let tokens = tokenize(input);
+
+	let mut day: Option<(Day, Range<usize>)> = None;
+	let mut time: Option<(NaiveTime, Range<usize>)> = None;
+	let mut months = 0_u32;
+	let mut delta = TimeDelta::zero();
+	let mut has_duration = false;
+
+	let mut i = 0;
+	while i < tokens.len() {
+		let token = &tokens[i];
+		let next = tokens.get(i + 1);
+
+		let mut found_day = None;
+		let mut found_time = None;
+		let mut used = 1;
+
+		match token.text.as_str() {
+			"in" | "and" | "on" => {}
+			"at" => match next {
+				None => return Err(TimeExprErrorKind::MissingTime.at(token.span.clone())),
+				Some(next) if parse_named_time(&next.text).is_some() => {}
+				Some(next) => match parse_clock(&tokens[i + 1..], true)? {
+					Some((t, clock_used)) => {
+						found_time = Some(t);
+						used += clock_used;
+					}
+					None => return Err(TimeExprErrorKind::UnrecognizedTimeFormat.at(next.span.clone())),
+				},
+			},
+			"today" => found_day = Some(Day::Today),
+			"tomorrow" | "tmr" | "tmrw" => found_day = Some(Day::Tomorrow),
+			text @ ("this" | "next") => {
+				let strict = text == "next";
+				let Some(next) = next else {
+					return Err(TimeExprErrorKind::MissingWeekday.at(token.span.clone()));
+				};
+
+				found_day = match (parse_weekday(&next.text), next.text.as_str()) {
+					(Some(weekday), _) => Some(Day::Weekday { weekday, strict }),
+					(None, "week") if strict => Some(Day::Later { months: 0, days: 7 }),
+					(None, "month") if strict => Some(Day::Later { months: 1, days: 0 }),
+					(None, "year") if strict => Some(Day::Later { months: 12, days: 0 }),
+					_ => return Err(TimeExprErrorKind::MissingWeekday.at(next.span.clone())),
+				};
+				used = 2;
+			}
+			text => {
+				if let Some(weekday) = parse_weekday(text) {
+					found_day = Some(Day::Weekday { weekday, strict: false });
+				} else if let Some(t) = parse_named_time(text) {
+					found_time = Some(t);
+				} else if text.starts_with(|c: char| c.is_ascii_digit()) && text.contains('-') {
+					let date = parse_date(text).map_err(|kind| kind.at(token.span.clone()))?;
+					found_day = Some(Day::Date(date));
+				} else if let Some((t, clock_used)) = parse_clock(&tokens[i..], false)? {
+					found_time = Some(t);
+					used = clock_used;
+				} else if let Some((unit, duration_used)) = parse_duration(&tokens[i..])? {
+					let span = token.span.start..tokens[i + duration_used - 1].span.end;
+					let out_of_range = || TimeExprErrorKind::OutOfRange.at(span.clone());
+
+					match unit {
+						Unit::Fixed(d) => delta = delta.checked_add(&d).ok_or_else(out_of_range)?,
+						Unit::Months(m) => months = months.checked_add(m).ok_or_else(out_of_range)?,
+					}
+
+					has_duration = true;
+					used = duration_used;
+				} else {
+					return Err(TimeExprErrorKind::UnknownWord.at(token.span.clone()));
+				}
+			}
+		}
+
+		let span = token.span.start..tokens[i + used - 1].span.end;
+
+		if let Some(found_day) = found_day {
+			if day.is_some() || has_duration {
+				return Err(TimeExprErrorKind::DuplicateDay.at(span));
+			}
+			day = Some((found_day, span.clone()));
+		}
+
+		if let Some(found_time) = found_time {
+			if time.is_some() {
+				return Err(TimeExprErrorKind::DuplicateTime.at(span));
+			}
+			time = Some((found_time, span.clone()));
+		}
+
+		if has_duration && day.is_some() {
+			return Err(TimeExprErrorKind::DuplicateDay.at(span));
+		}
+
+		i += used;
+	}
+
+	let whole = 0..input.len();
+	let out_of_range = || TimeExprErrorKind::OutOfRange.at(whole.clone());
+
+	if has_duration {
+		let mut date_time = now.checked_add_months(Months::new(months)).ok_or_else(out_of_range)?;
+		date_time = date_time.checked_add_signed(delta).ok_or_else(out_of_range)?;
+
+		if let Some((t, span)) = time {
+			date_time = resolve(tz, date_time.date_naive().and_time(t), span)?;
+		}
+
+		return Ok(date_time);
+	}
+
+	let today = now.date_naive();
+	let time_of_day = time.as_ref().map_or(now.time(), |(t, _)| *t);
+	let time_span = time.as_ref().map_or(whole.clone(), |(_, span)| span.clone());
+
+	let date = match &day {
+		None if time.is_none() => return Err(TimeExprErrorKind::Empty.at(whole)),
+		None | Some((Day::Today, _)) => today,
+		Some((Day::Tomorrow, _)) => today.succ_opt().ok_or_else(out_of_range)?,
+		Some((Day::Weekday { weekday, strict }, _)) => {
+			let days_ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+			let days_ahead = if *strict && days_ahead == 0 { 7 } else { days_ahead };
+			today + TimeDelta::days(days_ahead as i64)
+		}
+		Some((Day::Date(date), _)) => *date,
+		Some((Day::Later { months, days }, _)) => {
+			let date = today
+				.checked_add_months(Months::new(*months))
+				.ok_or_else(out_of_range)?;
+			date.checked_add_days(Days::new(*days)).ok_or_else(out_of_range)?
+		}
+	};
+
+	let date_time = resolve(tz, date.and_time(time_of_day), time_span)?;
+
+	if date_time <= *now {
+		// a time that already passed today means tomorrow, and "wednesday" on a wednesday means next week
+		let days = match &day {
+			None => 1,
+			Some((Day::Weekday { strict: false, .. }, _)) => 7,
+			_ => return Ok(date_time),
+		};
+
+		let naive = date.and_time(time_of_day) + TimeDelta::days(days);
+		return Ok(tz.timestamp_opt(local_timestamp(tz, naive), 0).unwrap());
+	}
+
+	Ok(date_time)
+}
+
+/// Parses a repeat rule like `every 1d`, `every 12h 30m`, `every week` or `every monday at 9am`.
+pub fn parse_recurrence(input: &str) -> Result<Recurrence, TimeExprError> {
+	let tokens = tokenize(input);
+	let whole = 0..input.len();
+
+	let Some(every) = tokens.first() else {
+		return Err(TimeExprErrorKind::MissingEvery.at(whole));
+	};
+	if every.text != "every" {
+		return Err(TimeExprErrorKind::MissingEvery.at(every.span.clone()));
+	}
+
+	let Some(first) = tokens.get(1) else {
+		return Err(TimeExprErrorKind::UnrecognizedRule.at(whole));
+	};
+
+	if let Some(weekday) = parse_weekday(&first.text) {
+		let rest = match tokens.get(2) {
+			Some(at) if at.text == "at" => &tokens[3..],
+			_ => &tokens[2..],
+		};
+
+		let Some(clock_token) = rest.first() else {
+			return Ok(Recurrence::Weekly(weekday, NaiveTime::MIN));
+		};
+
+		let (time, used) = match parse_named_time(&clock_token.text) {
+			Some(time) => (time, 1),
+			None => parse_clock(rest, true)?
+				.ok_or(TimeExprErrorKind::UnrecognizedTimeFormat.at(clock_token.span.clone()))?,
+		};
+
+		if let Some(extra) = rest.get(used) {
+			return Err(TimeExprErrorKind::UnknownWord.at(extra.span.clone()));
+		}
+
+		return Ok(Recurrence::Weekly(weekday, time));
+	}
+
+	let mut delta = TimeDelta::zero();
+
+	let mut i = 1;
+	while i < tokens.len() {
+		let token = &tokens[i];
+
+		// "every day", "every week"...
+		let (unit, used) = match parse_unit(&token.text) {
+			Some(unit) => (unit, 1),
+			None => match parse_duration(&tokens[i..])? {
+				Some(duration) => duration,
+				None if token.text == "and" => {
+					i += 1;
+					continue;
+				}
+				None => return Err(TimeExprErrorKind::UnknownWord.at(token.span.clone())),
+			},
+		};
+
+		let span = token.span.start..tokens[i + used - 1].span.end;
+		match unit {
+			// months and years don't have a fixed length
+			Unit::Months(_) => return Err(TimeExprErrorKind::UnrecognizedRule.at(span)),
+			Unit::Fixed(d) => {
+				delta = (delta.checked_add(&d)).ok_or(TimeExprErrorKind::OutOfRange.at(span))?;
+			}
+		}
+
+		i += used;
+	}
+
+	if delta.is_zero() {
+		return Err(TimeExprErrorKind::UnrecognizedRule.at(whole));
+	}
+
+	if delta.num_seconds() < MIN_REPEAT_SECS {
+		return Err(TimeExprErrorKind::TooOften.at(whole));
+	}
+
+	if delta.num_seconds() > MAX_REPEAT_SECS {
+		return Err(TimeExprErrorKind::OutOfRange.at(whole));
+	}
+
+	Ok(Recurrence::Interval(delta.num_seconds()))
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::{NaiveTime, TimeZone, Utc, Weekday};
+	use chrono_tz::Tz;
+
+	use super::{parse_recurrence, parse_time_expr, TimeExprErrorKind as K};
+	use crate::reminders::Recurrence;
+
+	fn date_time(tz: Tz, s: &str) -> chrono::DateTime<Tz> {
+		let naive = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+		tz.from_utc_datetime(&naive)
+	}
+
+	fn parse(tz: Tz, now: &str, input: &str) -> String {
+		let now = date_time(tz, now);
+		match parse_time_expr(&now, input) {
+			Ok(date_time) => date_time.with_timezone(&Utc).format("%Y-%m-%d %H:%M:%S").to_string(),
+			Err(e) => panic!("{input:?} should parse, got {e:?}"),
+		}
+	}
+
+	fn pie(s: &str) -> std::num::ParseIntError {
+		s.parse::<u8>().unwrap_err()
+	}
+
+	// Wednesday
+	const NOW: &str = "2025-01-15 10:30:00";
+
+	#[test]
+	fn time_expressions() {
+		let table = [
+			// durations
+			("1d 3h 10m", "2025-01-16 13:40:00"),
+			("23day", "2025-02-07 10:30:00"),
+			("35hrs 4min", "2025-01-16 21:34:00"),
+			("727secs", "2025-01-15 10:42:07"),
+			("5s", "2025-01-15 10:30:05"),
+			("  in   5m  ", "2025-01-15 10:35:00"),
+			("in 2 weeks", "2025-01-29 10:30:00"),
+			("in 2w", "2025-01-29 10:30:00"),
+			("in an hour", "2025-01-15 11:30:00"),
+			("in a day", "2025-01-16 10:30:00"),
+			("in 3mo", "2025-04-15 10:30:00"),
+			("in 1 month", "2025-02-15 10:30:00"),
+			("in 1y", "2026-01-15 10:30:00"),
+			("in 2 years", "2027-01-15 10:30:00"),
+			("in 2 days and 3 hours", "2025-01-17 13:30:00"),
+			("in 2 days, 3 hours", "2025-01-17 13:30:00"),
+			("in 2 days at 9", "2025-01-17 09:00:00"),
+			("IN 10 MINUTES", "2025-01-15 10:40:00"),
+			// days
+			("today", "2025-01-15 10:30:00"),
+			("tomorrow", "2025-01-16 10:30:00"),
+			("tmrw", "2025-01-16 10:30:00"),
+			("friday", "2025-01-17 10:30:00"),
+			("Fri", "2025-01-17 10:30:00"),
+			("on monday", "2025-01-20 10:30:00"),
+			("next friday", "2025-01-17 10:30:00"),
+			("this friday", "2025-01-17 10:30:00"),
+			("wednesday", "2025-01-22 10:30:00"),
+			("next wednesday", "2025-01-22 10:30:00"),
+			("next week", "2025-01-22 10:30:00"),
+			("next month", "2025-02-15 10:30:00"),
+			("next year", "2026-01-15 10:30:00"),
+			("2027-06-10", "2027-06-10 10:30:00"),
+			// times of day
+			("at 17:30", "2025-01-15 17:30:00"),
+			("17:30", "2025-01-15 17:30:00"),
+			("at 9", "2025-01-16 09:00:00"),
+			("at 10:30", "2025-01-16 10:30:00"),
+			("at 10:31", "2025-01-15 10:31:00"),
+			("at 12:23:45", "2025-01-15 12:23:45"),
+			("9pm", "2025-01-15 21:00:00"),
+			("9 pm", "2025-01-15 21:00:00"),
+			("at 9:15am", "2025-01-16 09:15:00"),
+			("12pm", "2025-01-15 12:00:00"),
+			("12am", "2025-01-16 00:00:00"),
+			("noon", "2025-01-15 12:00:00"),
+			("at noon", "2025-01-15 12:00:00"),
+			("midnight", "2025-01-16 00:00:00"),
+			("eod", "2025-01-15 17:00:00"),
+			// days and times together
+			("tomorrow at 9", "2025-01-16 09:00:00"),
+			("tomorrow at 9pm", "2025-01-16 21:00:00"),
+			("Tomorrow 9:15am", "2025-01-16 09:15:00"),
+			("9am tomorrow", "2025-01-16 09:00:00"),
+			("tomorrow noon", "2025-01-16 12:00:00"),
+			("today at 17:30", "2025-01-15 17:30:00"),
+			("today at 9", "2025-01-15 09:00:00"),
+			("wednesday at 18:00", "2025-01-15 18:00:00"),
+			("this wed 18:00", "2025-01-15 18:00:00"),
+			("next wednesday at 18:00", "2025-01-22 18:00:00"),
+			("on monday at 8am", "2025-01-20 08:00:00"),
+			("next friday eod", "2025-01-17 17:00:00"),
+			("2027-06-10 12:23", "2027-06-10 12:23:00"),
+			("2027-06-10 12:23:45", "2027-06-10 12:23:45"),
+			("2027-06-10 at 12am", "2027-06-10 00:00:00"),
+		];
+
+		for (input, expected) in table {
+			assert_eq!(parse(Tz::UTC, NOW, input), expected, "parsing {input:?}");
+		}
+	}
+
+	#[test]
+	fn time_expressions_in_time_zones() {
+		let table = [
+			// Saturday, the day before DST starts in Paris
+			(
+				Tz::Europe__Paris,
+				"2025-03-29 12:00:00",
+				"at 14:00",
+				"2025-03-29 13:00:00",
+			),
+			(
+				Tz::Europe__Paris,
+				"2025-03-29 12:00:00",
+				"tomorrow at 12:00",
+				"2025-03-30 10:00:00",
+			),
+			(Tz::Europe__Paris, "2025-03-29 12:00:00", "in 1d", "2025-03-30 12:00:00"),
+			(
+				Tz::Europe__Paris,
+				"2025-03-29 12:00:00",
+				"tomorrow at 3:00",
+				"2025-03-30 01:00:00",
+			),
+			// Saturday, the day before DST ends in Paris: 02:30 happens twice, the first one wins
+			(
+				Tz::Europe__Paris,
+				"2025-10-25 12:00:00",
+				"tomorrow at 2:30",
+				"2025-10-26 00:30:00",
+			),
+			(
+				Tz::Europe__Paris,
+				"2025-10-25 12:00:00",
+				"tomorrow at 12:00",
+				"2025-10-26 11:00:00",
+			),
+			// already past midnight in Tokyo
+			(
+				Tz::Asia__Tokyo,
+				"2025-01-15 20:00:00",
+				"today at 9",
+				"2025-01-16 00:00:00",
+			),
+			(
+				Tz::Asia__Tokyo,
+				"2025-01-15 20:00:00",
+				"friday noon",
+				"2025-01-17 03:00:00",
+			),
+			(
+				Tz::America__New_York,
+				"2025-01-15 20:00:00",
+				"eod",
+				"2025-01-15 22:00:00",
+			),
+			(
+				Tz::America__New_York,
+				"2025-01-15 23:00:00",
+				"eod",
+				"2025-01-16 22:00:00",
+			),
+		];
+
+		for (tz, now, input, expected) in table {
+			assert_eq!(parse(tz, now, input), expected, "parsing {input:?} in {tz}");
+		}
+	}
+
+	#[test]
+	fn time_expression_errors() {
+		let table = [
+			("", K::Empty, ""),
+			("   ", K::Empty, "   "),
+			("in", K::Empty, "in"),
+			("blah", K::UnknownWord, "blah"),
+			("tomorrow at 9 please", K::UnknownWord, "please"),
+			("in 5", K::MissingUnit, "5"),
+			("5x", K::UnknownUnit, "5x"),
+			("tomorrow at", K::MissingTime, "at"),
+			("at lunch", K::UnrecognizedTimeFormat, "lunch"),
+			("at 1:2:3:4", K::UnrecognizedTimeFormat, "1:2:3:4"),
+			("next", K::MissingWeekday, "next"),
+			("next blah", K::MissingWeekday, "blah"),
+			("this week", K::MissingWeekday, "week"),
+			("tomorrow friday", K::DuplicateDay, "friday"),
+			("in 2 days tomorrow", K::DuplicateDay, "tomorrow"),
+			("tomorrow in 2 days", K::DuplicateDay, "2 days"),
+			("noon at 9", K::DuplicateTime, "at 9"),
+			("2025-01", K::UnrecognizedDateFormat, "2025-01"),
+			("2x25-01-01", K::ParseYear(pie("x")), "2x25-01-01"),
+			("2025-x-01", K::ParseMonth(pie("x")), "2025-x-01"),
+			("2025-01-x", K::ParseDay(pie("x")), "2025-01-x"),
+			("2025-13-01", K::InvalidMonth, "2025-13-01"),
+			("2025-01-32", K::InvalidDay, "2025-01-32"),
+			("2025-02-30", K::InvalidDate, "2025-02-30"),
+			("at 24:00", K::InvalidHour, "24:00"),
+			("at 25", K::InvalidHour, "25"),
+			("at 12:60", K::InvalidMin, "12:60"),
+			("at 12:00:60", K::InvalidSec, "12:00:60"),
+			("at 12:x", K::ParseMin(pie("x")), "12:x"),
+			("13pm", K::InvalidHour, "13pm"),
+			("0 am", K::InvalidHour, "0 am"),
+			// too big for the u32 numbers are read as
+			(
+				"in 99999999999s",
+				K::ParseNumber("99999999999".parse::<u32>().unwrap_err()),
+				"99999999999s",
+			),
+			("in 100000000 years", K::OutOfRange, "in 100000000 years"),
+			("in 3000000000s", K::OutOfRange, "3000000000s"),
+		];
+
+		let now = date_time(Tz::UTC, NOW);
+		for (input, kind, span) in table {
+			let e = parse_time_expr(&now, input).expect_err(input);
+			assert_eq!(e.kind, kind, "parsing {input:?}");
+			assert_eq!(&input[e.span], span, "parsing {input:?}");
+		}
+	}
+
+	#[test]
+	fn skipped_time_is_an_error() {
+		let now = date_time(Tz::Europe__Paris, "2025-03-29 12:00:00");
+		let input = "tomorrow at 2:30";
+		let e = parse_time_expr(&now, input).unwrap_err();
+		assert_eq!(e.kind, K::SkippedTime);
+		assert_eq!(&input[e.span], "at 2:30");
+	}
+
+	#[test]
+	fn recurrences() {
+		let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+		let table = [
+			("every 1d", Recurrence::Interval(86400)),
+			("every day", Recurrence::Interval(86400)),
+			("every week", Recurrence::Interval(7 * 86400)),
+			("every 2 weeks", Recurrence::Interval(14 * 86400)),
+			("every 12h 30m", Recurrence::Interval(45000)),
+			("every 1h and 30 minutes", Recurrence::Interval(5400)),
+			("every minute", Recurrence::Interval(60)),
+			("every monday", Recurrence::Weekly(Weekday::Mon, NaiveTime::MIN)),
+			("every monday 09:00", Recurrence::Weekly(Weekday::Mon, nine)),
+			("Every Monday at 9am", Recurrence::Weekly(Weekday::Mon, nine)),
+			(
+				"every fri at 5 pm",
+				Recurrence::Weekly(Weekday::Fri, nine + chrono::TimeDelta::hours(8)),
+			),
+			(
+				"every sunday noon",
+				Recurrence::Weekly(Weekday::Sun, nine + chrono::TimeDelta::hours(3)),
+			),
+		];
+
+		for (input, expected) in table {
+			assert_eq!(parse_recurrence(input), Ok(expected), "parsing {input:?}");
+		}
+	}
+
+	#[test]
+	fn recurrence_errors() {
+		let table = [
+			("", K::MissingEvery, ""),
+			("each day", K::MissingEvery, "each"),
+			("every", K::UnrecognizedRule, "every"),
+			("every 30s", K::TooOften, "every 30s"),
+			("every 3000000000s", K::OutOfRange, "3000000000s"),
+			("every 20000000 weeks", K::OutOfRange, "every 20000000 weeks"),
+			("every 36501 days", K::OutOfRange, "every 36501 days"),
+			("every 0m", K::UnrecognizedRule, "every 0m"),
+			("every 1mo", K::UnrecognizedRule, "1mo"),
+			("every blah", K::UnknownWord, "blah"),
+			("every 5", K::MissingUnit, "5"),
+			("every monday at 25:00", K::InvalidHour, "25:00"),
+			("every monday at lunch", K::UnrecognizedTimeFormat, "lunch"),
+			("every monday 9am sharp", K::UnknownWord, "sharp"),
+		];
+
+		for (input, kind, span) in table {
+			let e = parse_recurrence(input).expect_err(input);
+			assert_eq!(e.kind, kind, "parsing {input:?}");
+			assert_eq!(&input[e.span], span, "parsing {input:?}");
+		}
+	}
+
+	#[test]
+	fn error_message_points_at_the_problem() {
+		let now = date_time(Tz::UTC, NOW);
+		let input = "tomorrow at 25:00";
+		let e = parse_time_expr(&now, input).unwrap_err();
+
+		let message = super::error_message(&e, input);
+		assert!(
+			message.ends_with("```\ntomorrow at 25:00\n            ^^^^^\n```"),
+			"{message}"
+		);
+
+		// backticks take more bytes once replaced, but still one character
+		let input = "`a` `b`";
+		let message = super::error_message(&K::UnknownWord.at(4..7), input);
+		assert!(message.ends_with("```\nˋaˋ ˋbˋ\n    ^^^\n```"), "{message}");
+	}
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, NaiveDateTime, Offset, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use serenity::all::UserId;
+
+use crate::db::Db;
+
+/// Time zone the user asked for with `/timezone`, or UTC if they never did.
+pub fn user_timezone(db: &Db, user_id: UserId) -> Tz {
+	db.timezone(user_id).unwrap().unwrap_or(Tz::UTC)
+}
+
+/// Formats a timestamp as a plain date and time in the given time zone, like `2027-06-10 12:23 CEST`.
+pub fn format_in_timezone(timestamp: i64, tz: Tz) -> String {
+	let date_time = tz.from_utc_datetime(&DateTime::<Utc>::from_timestamp(timestamp, 0).unwrap().naive_utc());
+	date_time.format("%Y-%m-%d %H:%M %Z").to_string()
+}
+
+/// Timestamp of a wall-clock date and time in the given time zone.
+///
+/// When clocks go back, the earliest of the two matching instants is picked.
+/// When clocks skip forward over the time, it is pushed forward as much as the clocks were,
+/// be it by an hour, two, or a whole day.
+pub fn local_timestamp(tz: Tz, naive: NaiveDateTime) -> i64 {
+	if let Some(date_time) = tz.from_local_datetime(&naive).earliest() {
+		return date_time.timestamp();
+	}
+
+	// read it with the offset from before the gap, a couple of days earlier being well clear of it
+	let before = naive.checked_sub_signed(TimeDelta::days(2)).unwrap_or(naive);
+	let offset = tz.offset_from_utc_datetime(&before).fix().local_minus_utc();
+	naive.and_utc().timestamp() - offset as i64
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::NaiveDate;
+
+	use super::*;
+
+	fn naive(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+		NaiveDate::from_ymd_opt(y, m, d)
+			.unwrap()
+			.and_hms_opt(h, min, 0)
+			.unwrap()
+	}
+
+	#[test]
+	fn skipped_times_are_pushed_past_the_gap() {
+		let paris = chrono_tz::Europe::Paris;
+		let after_gap = local_timestamp(paris, naive(2025, 3, 30, 3, 30));
+		assert_eq!(local_timestamp(paris, naive(2025, 3, 30, 2, 30)), after_gap);
+
+		// two hours skipped in spring
+		let troll = chrono_tz::Antarctica::Troll;
+		let after_gap = local_timestamp(troll, naive(2025, 3, 30, 3, 30));
+		assert_eq!(local_timestamp(troll, naive(2025, 3, 30, 1, 30)), after_gap);
+
+		// the whole 30th of December 2011 skipped
+		let apia = chrono_tz::Pacific::Apia;
+		let after_gap = local_timestamp(apia, naive(2011, 12, 31, 9, 0));
+		assert_eq!(local_timestamp(apia, naive(2011, 12, 30, 9, 0)), after_gap);
+	}
+}